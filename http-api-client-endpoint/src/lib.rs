@@ -1,8 +1,10 @@
 use core::time::Duration;
+use std::{borrow::Cow, net::SocketAddr};
 
 #[cfg(feature = "dyn-clone")]
 use dyn_clone::{clone_trait_object, DynClone};
 pub use http::{self, Request, Response};
+use http::{Method, Uri};
 
 pub type Body = Vec<u8>;
 pub const MIME_APPLICATION_JSON: &str = "application/json";
@@ -23,6 +25,32 @@ pub trait Endpoint: DynClone {
         &self,
         response: Response<Body>,
     ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError>;
+
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(core::any::type_name::<Self>())
+    }
+
+    fn metadata(&self) -> EndpointMetadata {
+        EndpointMetadata::default()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Compresses the rendered body, if the client is wrapped in a compression layer.
+    fn compression(&self) -> Option<RequestCompression> {
+        None
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Needs a client that follows redirects itself, e.g. one wrapped in a redirect layer.
+    fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        None
+    }
 }
 
 #[cfg(not(feature = "dyn-clone"))]
@@ -38,6 +66,32 @@ pub trait Endpoint {
         &self,
         response: Response<Body>,
     ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError>;
+
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(core::any::type_name::<Self>())
+    }
+
+    fn metadata(&self) -> EndpointMetadata {
+        EndpointMetadata::default()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Compresses the rendered body, if the client is wrapped in a compression layer.
+    fn compression(&self) -> Option<RequestCompression> {
+        None
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Needs a client that follows redirects itself, e.g. one wrapped in a redirect layer.
+    fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        None
+    }
 }
 
 #[cfg(feature = "dyn-clone")]
@@ -49,9 +103,15 @@ impl<RenderRequestError, ParseResponseOutput, ParseResponseError> core::fmt::Deb
         ParseResponseOutput = ParseResponseOutput,
         ParseResponseError = ParseResponseError,
     >
+where
+    RenderRequestError: std::error::Error + Send + Sync + 'static,
+    ParseResponseError: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("Endpoint").finish()
+        f.debug_struct("Endpoint")
+            .field("name", &self.name())
+            .field("metadata", &self.metadata())
+            .finish()
     }
 }
 
@@ -62,9 +122,15 @@ impl<RenderRequestError, ParseResponseOutput, ParseResponseError> core::fmt::Deb
             ParseResponseError = ParseResponseError,
         > + Send
         + Sync
+where
+    RenderRequestError: std::error::Error + Send + Sync + 'static,
+    ParseResponseError: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("Endpoint").finish()
+        f.debug_struct("Endpoint")
+            .field("name", &self.name())
+            .field("metadata", &self.metadata())
+            .finish()
     }
 }
 
@@ -95,12 +161,53 @@ pub trait RetryableEndpoint: DynClone {
         3
     }
 
+    /// Returning a reason retries the request after `Client::respond` failed,
+    /// which only happens if the request is idempotent or carries an `Idempotency-Key`.
+    fn retry_on_respond_failed(
+        &self,
+        _error: &(dyn std::error::Error + Send + Sync + 'static),
+        _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+    ) -> Option<Self::RetryReason> {
+        None
+    }
+
     fn next_retry_in(&self, retry: &RetryableEndpointRetry<Self::RetryReason>) -> Duration {
         match retry.count {
             0..=2 => Duration::from_millis(500),
             _ => Duration::from_secs(1),
         }
     }
+
+    /// Names the reason of a retry in traces, none by default.
+    fn retry_reason_label(&self, _reason: &Self::RetryReason) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(core::any::type_name::<Self>())
+    }
+
+    fn metadata(&self) -> EndpointMetadata {
+        EndpointMetadata::default()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Compresses the rendered body, if the client is wrapped in a compression layer.
+    fn compression(&self) -> Option<RequestCompression> {
+        None
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Needs a client that follows redirects itself, e.g. one wrapped in a redirect layer.
+    fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        None
+    }
 }
 
 #[cfg(not(feature = "dyn-clone"))]
@@ -127,12 +234,53 @@ pub trait RetryableEndpoint {
         3
     }
 
+    /// Returning a reason retries the request after `Client::respond` failed,
+    /// which only happens if the request is idempotent or carries an `Idempotency-Key`.
+    fn retry_on_respond_failed(
+        &self,
+        _error: &(dyn std::error::Error + Send + Sync + 'static),
+        _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+    ) -> Option<Self::RetryReason> {
+        None
+    }
+
     fn next_retry_in(&self, retry: &RetryableEndpointRetry<Self::RetryReason>) -> Duration {
         match retry.count {
-            0 | 1 | 2 => Duration::from_millis(500),
+            0..=2 => Duration::from_millis(500),
             _ => Duration::from_secs(1),
         }
     }
+
+    /// Names the reason of a retry in traces, none by default.
+    fn retry_reason_label(&self, _reason: &Self::RetryReason) -> Option<Cow<'static, str>> {
+        None
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(core::any::type_name::<Self>())
+    }
+
+    fn metadata(&self) -> EndpointMetadata {
+        EndpointMetadata::default()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Compresses the rendered body, if the client is wrapped in a compression layer.
+    fn compression(&self) -> Option<RequestCompression> {
+        None
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Needs a client that follows redirects itself, e.g. one wrapped in a redirect layer.
+    fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        None
+    }
 }
 
 #[cfg(feature = "dyn-clone")]
@@ -145,9 +293,16 @@ impl<RetryReason, RenderRequestError, ParseResponseOutput, ParseResponseError> c
         ParseResponseOutput = ParseResponseOutput,
         ParseResponseError = ParseResponseError,
     >
+where
+    RetryReason: Send + Sync + Clone,
+    RenderRequestError: std::error::Error + Send + Sync + 'static,
+    ParseResponseError: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("RetryableEndpoint").finish()
+        f.debug_struct("RetryableEndpoint")
+            .field("name", &self.name())
+            .field("metadata", &self.metadata())
+            .finish()
     }
 }
 
@@ -159,9 +314,161 @@ impl<RetryReason, RenderRequestError, ParseResponseOutput, ParseResponseError> c
             ParseResponseError = ParseResponseError,
         > + Send
         + Sync
+where
+    RetryReason: Send + Sync + Clone,
+    RenderRequestError: std::error::Error + Send + Sync + 'static,
+    ParseResponseError: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("RetryableEndpoint").finish()
+        f.debug_struct("RetryableEndpoint")
+            .field("name", &self.name())
+            .field("metadata", &self.metadata())
+            .finish()
+    }
+}
+
+//
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointMetadata {
+    pub operation_id: Option<Cow<'static, str>>,
+    pub tags: Vec<Cow<'static, str>>,
+    /// `None` means it is derived from the request method.
+    pub idempotent: Option<bool>,
+}
+
+impl EndpointMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn operation_id(mut self, operation_id: impl Into<Cow<'static, str>>) -> Self {
+        self.operation_id = Some(operation_id.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<Cow<'static, str>>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = Some(idempotent);
+        self
+    }
+
+    pub fn is_idempotent(&self, method: &Method) -> bool {
+        self.idempotent.unwrap_or_else(|| method.is_idempotent())
+    }
+}
+
+//
+/// Request extension that overrides the client's total timeout for a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestTimeout(pub Duration);
+
+/// Request extension marking a request as safe to send more than once, set from [`EndpointMetadata::is_idempotent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Idempotent;
+
+/// Request extension that overrides the client's maximum response body size for a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaxResponseBodySize(pub usize);
+
+/// Request extension that overrides the client's proxy for a single request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RequestProxy {
+    Disabled,
+    Url(Uri),
+}
+
+/// Request extension that overrides how the client follows redirects for a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RedirectPolicy {
+    None,
+    Limit(usize),
+    /// Like `Limit`, but a redirect to another origin is returned instead of followed.
+    SameOrigin(usize),
+}
+
+/// Request extension that pins the HTTP version for a single request instead of negotiating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum VersionPreference {
+    Http1Only,
+    Http2PriorKnowledge,
+}
+
+/// Request extension with the TLS client certificate to present for a single request.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum TlsIdentity {
+    /// PEM encoded certificate chain and PKCS#8 private key.
+    Pem { cert: Vec<u8>, key: Vec<u8> },
+    /// DER encoded PKCS#12 archive.
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+impl core::fmt::Debug for TlsIdentity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Pem { .. } => f.debug_struct("Pem").finish_non_exhaustive(),
+            Self::Pkcs12 { .. } => f.debug_struct("Pkcs12").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Response extension with the address of the peer the response came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteAddr(pub SocketAddr);
+
+/// Response extension with the URI the response came from, after following redirects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveUri(pub Uri);
+
+/// Response extension with what the backend measured, phases in the order they happen.
+///
+/// `time_to_first_byte` and `total` are measured from the start of the request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseTimings {
+    pub dns_lookup: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls_handshake: Option<Duration>,
+    pub time_to_first_byte: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+/// Request extension that asks for the body to be compressed once it reaches `min_size` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestCompression {
+    pub encoding: ContentCoding,
+    pub min_size: usize,
+}
+
+impl RequestCompression {
+    pub fn new(encoding: ContentCoding, min_size: usize) -> Self {
+        Self { encoding, min_size }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl ContentCoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
     }
 }
 
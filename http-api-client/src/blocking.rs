@@ -0,0 +1,319 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use http_api_client_endpoint::{
+    Body, Endpoint, Request, Response, RetryableEndpoint, RetryableEndpointRetry,
+};
+
+#[cfg(feature = "tracing")]
+use crate::{instrumentation, EndpointError};
+use crate::{
+    respond::{self, next_retry, prepare_request, set_idempotency_key, EndpointOptions},
+    ClientMetrics, ClientRespondEndpointError, RetryableClientRespondEndpointUntilDoneError,
+    IDEMPOTENCY_KEY,
+};
+
+type RespondEndpointResult<C, EP> = Result<
+    <EP as Endpoint>::ParseResponseOutput,
+    ClientRespondEndpointError<
+        <C as BlockingClient>::RespondError,
+        <EP as Endpoint>::RenderRequestError,
+        <EP as Endpoint>::ParseResponseError,
+    >,
+>;
+
+type RespondEndpointUntilDoneResult<C, EP> = Result<
+    <EP as RetryableEndpoint>::ParseResponseOutput,
+    RetryableClientRespondEndpointUntilDoneError<
+        <C as BlockingClient>::RespondError,
+        <EP as RetryableEndpoint>::RenderRequestError,
+        <EP as RetryableEndpoint>::ParseResponseError,
+    >,
+>;
+
+/// Synchronous counterpart of [`Client`](crate::Client) and [`RetryableClient`](crate::RetryableClient).
+pub trait BlockingClient {
+    type RespondError: std::error::Error + Send + Sync + 'static;
+
+    fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError>;
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        None
+    }
+
+    fn sleep(&self, dur: Duration) {
+        std::thread::sleep(dur)
+    }
+
+    fn respond_endpoint<EP>(&self, endpoint: &EP) -> RespondEndpointResult<Self, EP>
+    where
+        EP: Endpoint,
+    {
+        self.respond_endpoint_with_callback(endpoint, |req| req, |_| {})
+    }
+
+    fn respond_endpoint_with_callback<EP, PreRCB, PostRCB>(
+        &self,
+        endpoint: &EP,
+        pre_request_callback: PreRCB,
+        post_request_callback: PostRCB,
+    ) -> RespondEndpointResult<Self, EP>
+    where
+        EP: Endpoint,
+        PreRCB: FnMut(Request<Body>) -> Request<Body>,
+        PostRCB: FnMut(&Response<Body>),
+    {
+        self.respond_dyn_endpoint_with_callback(
+            endpoint,
+            pre_request_callback,
+            post_request_callback,
+        )
+    }
+
+    fn respond_dyn_endpoint<RRE, PRO, PRE>(
+        &self,
+        endpoint: &dyn Endpoint<
+            RenderRequestError = RRE,
+            ParseResponseOutput = PRO,
+            ParseResponseError = PRE,
+        >,
+    ) -> Result<PRO, ClientRespondEndpointError<Self::RespondError, RRE, PRE>>
+    where
+        RRE: std::error::Error + Send + Sync + 'static,
+        PRE: std::error::Error + Send + Sync + 'static,
+    {
+        self.respond_dyn_endpoint_with_callback(endpoint, |req| req, |_| {})
+    }
+
+    fn respond_dyn_endpoint_with_callback<RRE, PRO, PRE, PreRCB, PostRCB>(
+        &self,
+        endpoint: &dyn Endpoint<
+            RenderRequestError = RRE,
+            ParseResponseOutput = PRO,
+            ParseResponseError = PRE,
+        >,
+        mut pre_request_callback: PreRCB,
+        mut post_request_callback: PostRCB,
+    ) -> Result<PRO, ClientRespondEndpointError<Self::RespondError, RRE, PRE>>
+    where
+        RRE: std::error::Error + Send + Sync + 'static,
+        PRE: std::error::Error + Send + Sync + 'static,
+        PreRCB: FnMut(Request<Body>) -> Request<Body>,
+        PostRCB: FnMut(&Response<Body>),
+    {
+        let endpoint_name = endpoint.name();
+        let options = EndpointOptions::of(endpoint);
+
+        #[cfg(feature = "tracing")]
+        let span = instrumentation::endpoint_span(&endpoint_name, &options.metadata);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let ret = (|| {
+            let mut request = endpoint
+                .render_request()
+                .map_err(ClientRespondEndpointError::EndpointRenderRequestFailed)?;
+
+            prepare_request(&options, &mut request);
+
+            let request = pre_request_callback(request);
+
+            #[cfg(feature = "tracing")]
+            instrumentation::record_request(&span, &request);
+
+            let method = request.method().to_owned();
+            let started_at = Instant::now();
+
+            let ret = self.respond(request);
+
+            respond::record_request(self.metrics(), &endpoint_name, &method, started_at, &ret);
+
+            let response = ret.map_err(ClientRespondEndpointError::RespondFailed)?;
+
+            #[cfg(feature = "tracing")]
+            instrumentation::record_response(&span, &response);
+
+            post_request_callback(&response);
+
+            endpoint
+                .parse_response(response)
+                .map_err(ClientRespondEndpointError::EndpointParseResponseFailed)
+        })();
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &ret {
+            instrumentation::record_error(&span, err.kind());
+            tracing::debug!(
+                error = %EndpointError { endpoint: endpoint_name, error: err },
+                "respond endpoint failed"
+            );
+        }
+
+        ret
+    }
+
+    fn respond_endpoint_until_done<EP>(
+        &self,
+        endpoint: &EP,
+    ) -> RespondEndpointUntilDoneResult<Self, EP>
+    where
+        EP: RetryableEndpoint,
+    {
+        self.respond_endpoint_until_done_with_callback(endpoint, |req, _| req, |_, _| {})
+    }
+
+    fn respond_endpoint_until_done_with_callback<EP, PreRCB, PostRCB>(
+        &self,
+        endpoint: &EP,
+        mut pre_request_callback: PreRCB,
+        mut post_request_callback: PostRCB,
+    ) -> RespondEndpointUntilDoneResult<Self, EP>
+    where
+        EP: RetryableEndpoint,
+        PreRCB:
+            FnMut(Request<Body>, Option<&RetryableEndpointRetry<EP::RetryReason>>) -> Request<Body>,
+        PostRCB: FnMut(&Response<Body>, Option<&RetryableEndpointRetry<EP::RetryReason>>),
+    {
+        let endpoint_name = endpoint.name();
+        let options = EndpointOptions::of_retryable(endpoint);
+
+        #[cfg(feature = "tracing")]
+        let span = instrumentation::endpoint_until_done_span(&endpoint_name, &options.metadata);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let mut retry = None;
+        let mut idempotency_key = None;
+
+        let ret = loop {
+            let attempt = (|| {
+                #[cfg(feature = "tracing")]
+                let attempt_span = instrumentation::endpoint_attempt_span(
+                    retry
+                        .as_ref()
+                        .map(|x: &RetryableEndpointRetry<_>| x.count)
+                        .unwrap_or(0),
+                );
+                #[cfg(feature = "tracing")]
+                let _enter = attempt_span.enter();
+
+                let mut request = endpoint.render_request(retry.as_ref()).map_err(
+                    RetryableClientRespondEndpointUntilDoneError::EndpointRenderRequestFailed,
+                )?;
+
+                let idempotent = prepare_request(&options, &mut request);
+                if !idempotent {
+                    set_idempotency_key(&mut request, &mut idempotency_key);
+                }
+
+                let request = pre_request_callback(request, retry.as_ref());
+
+                let retryable_on_respond_failed =
+                    idempotent || request.headers().contains_key(IDEMPOTENCY_KEY);
+
+                #[cfg(feature = "tracing")]
+                instrumentation::record_request(&attempt_span, &request);
+
+                let method = request.method().to_owned();
+                let started_at = Instant::now();
+
+                let ret = self.respond(request);
+
+                respond::record_request(self.metrics(), &endpoint_name, &method, started_at, &ret);
+
+                let response = match ret {
+                    Ok(response) => response,
+                    Err(err) => {
+                        if retryable_on_respond_failed {
+                            if let Some(reason) =
+                                endpoint.retry_on_respond_failed(&err, retry.as_ref())
+                            {
+                                return Ok(Err(reason));
+                            }
+                        }
+                        return Err(RetryableClientRespondEndpointUntilDoneError::RespondFailed(
+                            err,
+                        ));
+                    }
+                };
+
+                #[cfg(feature = "tracing")]
+                instrumentation::record_response(&attempt_span, &response);
+
+                post_request_callback(&response, retry.as_ref());
+
+                endpoint.parse_response(response, retry.as_ref()).map_err(
+                    RetryableClientRespondEndpointUntilDoneError::EndpointParseResponseFailed,
+                )
+            })();
+
+            let reason = match attempt {
+                Ok(Ok(output)) => break Ok(output),
+                Ok(Err(reason)) => reason,
+                Err(err) => break Err(err),
+            };
+
+            match next_retry(endpoint, &endpoint_name, self.metrics(), &mut retry, reason) {
+                Some(dur) => self.sleep(dur),
+                None => break Err(RetryableClientRespondEndpointUntilDoneError::ReachedMaxRetries),
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &ret {
+            instrumentation::record_error(&span, err.kind());
+            tracing::debug!(
+                error = %EndpointError { endpoint: endpoint_name, error: err },
+                "respond endpoint failed"
+            );
+        }
+
+        ret
+    }
+}
+
+//
+#[cfg(feature = "tokio")]
+pub use self::tokio_adapter::TokioBlockingClient;
+
+#[cfg(feature = "tokio")]
+mod tokio_adapter {
+    use super::*;
+
+    use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
+
+    use crate::Client;
+
+    /// Runs an async [`Client`] on a private current-thread tokio runtime.
+    ///
+    /// Must not be used from within an async context.
+    #[derive(Debug)]
+    pub struct TokioBlockingClient<C> {
+        pub client: C,
+        runtime: Runtime,
+    }
+
+    impl<C> TokioBlockingClient<C> {
+        pub fn new(client: C) -> Result<Self, std::io::Error> {
+            Ok(Self {
+                client,
+                runtime: RuntimeBuilder::new_current_thread().enable_all().build()?,
+            })
+        }
+    }
+
+    impl<C> BlockingClient for TokioBlockingClient<C>
+    where
+        C: Client + Sync,
+    {
+        type RespondError = C::RespondError;
+
+        fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+            self.runtime.block_on(self.client.respond(request))
+        }
+
+        fn metrics(&self) -> Option<&dyn ClientMetrics> {
+            self.client.metrics()
+        }
+    }
+}
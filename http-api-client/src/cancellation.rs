@@ -0,0 +1,23 @@
+use core::{
+    future::{poll_fn, Future},
+    pin::pin,
+    task::Poll,
+};
+
+/// Resolves to `None` as soon as `cancellation` completes, dropping `fut`.
+pub(crate) async fn or_cancelled<F, C>(fut: F, cancellation: C) -> Option<F::Output>
+where
+    F: Future,
+    C: Future<Output = ()>,
+{
+    let mut fut = pin!(fut);
+    let mut cancellation = pin!(cancellation);
+
+    poll_fn(|cx| {
+        if cancellation.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        fut.as_mut().poll(cx).map(Some)
+    })
+    .await
+}
@@ -0,0 +1,394 @@
+use core::{
+    future::poll_fn,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Poll, Waker},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::{ACCEPT, AUTHORIZATION, COOKIE},
+        HeaderName, HeaderValue, Method, Uri,
+    },
+    Body, MaxResponseBodySize, RedirectPolicy, Request, RequestProxy, RequestTimeout, Response,
+    TlsIdentity, VersionPreference,
+};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+type Flights<E> = Mutex<HashMap<Key, Arc<Flight<E>>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    method: Method,
+    uri: Uri,
+    headers: Vec<Option<HeaderValue>>,
+    // The extensions changing what the response may look like, as only the first caller's request is sent.
+    timeout: Option<RequestTimeout>,
+    max_response_body_size: Option<MaxResponseBodySize>,
+    redirect_policy: Option<RedirectPolicy>,
+    proxy: Option<RequestProxy>,
+    version_preference: Option<VersionPreference>,
+    tls_identity: Option<TlsIdentity>,
+}
+
+/// Sends concurrent `GET` and `HEAD` requests with the same URI and `key_headers` only once,
+/// handing a copy of the response to each caller, which still parses it on its own.
+///
+/// The [`RequestTimeout`], [`MaxResponseBodySize`], [`RedirectPolicy`], [`RequestProxy`],
+/// [`VersionPreference`] and [`TlsIdentity`] extensions have to match too,
+/// other extensions of the callers that didn't send the request are ignored.
+/// Nothing is kept once the response arrived.
+#[derive(Debug, Clone)]
+pub struct CoalescingClient<C>
+where
+    C: Client,
+{
+    pub client: C,
+    /// Requests only share a response when these are the same, by default `Authorization`, `Cookie` and `Accept`.
+    pub key_headers: Vec<HeaderName>,
+    flights: Arc<Flights<C::RespondError>>,
+}
+
+#[derive(Debug)]
+struct Flight<E> {
+    state: Mutex<FlightState<E>>,
+    followers: AtomicUsize,
+}
+
+#[derive(Debug)]
+enum FlightState<E> {
+    /// The wakers of the callers waiting for the response, by follower id.
+    Running(HashMap<usize, Waker>),
+    Done(Result<Response<Body>, Arc<E>>),
+    /// The caller sending the request was dropped.
+    Abandoned,
+}
+
+impl<C> CoalescingClient<C>
+where
+    C: Client,
+{
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            key_headers: vec![AUTHORIZATION, COOKIE, ACCEPT],
+            flights: Default::default(),
+        }
+    }
+
+    pub fn with_key_header(mut self, name: HeaderName) -> Self {
+        self.key_headers.push(name);
+        self
+    }
+}
+
+struct FlightGuard<'a, E> {
+    flights: &'a Flights<E>,
+    key: Key,
+    flight: Arc<Flight<E>>,
+}
+
+impl<E> FlightGuard<'_, E> {
+    fn land(&self, state: FlightState<E>) {
+        let mut flights = self.flights.lock().expect("poisoned");
+        if flights
+            .get(&self.key)
+            .is_some_and(|x| Arc::ptr_eq(x, &self.flight))
+        {
+            flights.remove(&self.key);
+        }
+        drop(flights);
+
+        let prev = core::mem::replace(&mut *self.flight.state.lock().expect("poisoned"), state);
+        if let FlightState::Running(wakers) = prev {
+            wakers.into_values().for_each(Waker::wake);
+        }
+    }
+}
+
+impl<E> Drop for FlightGuard<'_, E> {
+    fn drop(&mut self) {
+        let running = matches!(
+            &*self.flight.state.lock().expect("poisoned"),
+            FlightState::Running(_)
+        );
+        if running {
+            self.land(FlightState::Abandoned);
+        }
+    }
+}
+
+/// Unregisters the waker of a caller dropped while waiting.
+struct FollowerGuard<'a, E> {
+    flight: &'a Flight<E>,
+    id: usize,
+}
+
+impl<E> Drop for FollowerGuard<'_, E> {
+    fn drop(&mut self) {
+        if let FlightState::Running(wakers) = &mut *self.flight.state.lock().expect("poisoned") {
+            wakers.remove(&self.id);
+        }
+    }
+}
+
+#[async_trait]
+impl<C> Client for CoalescingClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = Arc<C::RespondError>;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        if !matches!(*request.method(), Method::GET | Method::HEAD) {
+            return self.client.respond(request).await.map_err(Arc::new);
+        }
+
+        let key = Key {
+            method: request.method().to_owned(),
+            uri: request.uri().to_owned(),
+            headers: self
+                .key_headers
+                .iter()
+                .map(|name| request.headers().get(name).cloned())
+                .collect(),
+            timeout: request.extensions().get().copied(),
+            max_response_body_size: request.extensions().get().copied(),
+            redirect_policy: request.extensions().get().copied(),
+            proxy: request.extensions().get().cloned(),
+            version_preference: request.extensions().get().copied(),
+            tls_identity: request.extensions().get().cloned(),
+        };
+
+        loop {
+            let (flight, leading) = {
+                let mut flights = self.flights.lock().expect("poisoned");
+                match flights.get(&key) {
+                    Some(flight) => (flight.to_owned(), false),
+                    None => {
+                        let flight = Arc::new(Flight {
+                            state: Mutex::new(FlightState::Running(HashMap::new())),
+                            followers: AtomicUsize::new(0),
+                        });
+                        flights.insert(key.to_owned(), flight.clone());
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leading {
+                let guard = FlightGuard {
+                    flights: &self.flights,
+                    key,
+                    flight,
+                };
+                let ret = self.client.respond(request).await.map_err(Arc::new);
+                guard.land(FlightState::Done(ret.clone()));
+                return ret;
+            }
+
+            let follower = FollowerGuard {
+                flight: &flight,
+                id: flight.followers.fetch_add(1, Ordering::Relaxed),
+            };
+            let ret = poll_fn(|cx| match &mut *flight.state.lock().expect("poisoned") {
+                FlightState::Running(wakers) => {
+                    match wakers.get_mut(&follower.id) {
+                        Some(waker) => waker.clone_from(cx.waker()),
+                        None => {
+                            wakers.insert(follower.id, cx.waker().to_owned());
+                        }
+                    }
+                    Poll::Pending
+                }
+                FlightState::Done(ret) => Poll::Ready(Some(ret.to_owned())),
+                // Try again, sending the request if no one else does.
+                FlightState::Abandoned => Poll::Ready(None),
+            })
+            .await;
+            if let Some(ret) = ret {
+                return ret;
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for CoalescingClient<C>
+where
+    C: Client + WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::{sync::atomic::AtomicBool, task::Context, time::Duration};
+    use std::io::Error as IoError;
+
+    use http_api_client_endpoint::Endpoint;
+
+    // Answers with the call index once released.
+    #[derive(Debug, Default)]
+    struct MyClient {
+        calls: AtomicUsize,
+        released: AtomicBool,
+    }
+    #[async_trait]
+    impl Client for MyClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            _request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            poll_fn(|_| match self.released.load(Ordering::SeqCst) {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            })
+            .await;
+            Ok(Response::new(call.to_string().into_bytes()))
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint(&'static str);
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = String;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::get(self.0).body(vec![]).unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(format!(
+                "{} {}",
+                self.0,
+                String::from_utf8(response.into_body()).unwrap()
+            ))
+        }
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let client = CoalescingClient::new(MyClient::default());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut a = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        let mut b = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        let mut c = client.respond_endpoint(&MyEndpoint("http://c.test/"));
+        for fut in [&mut a, &mut b, &mut c] {
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(client.client.calls.load(Ordering::SeqCst), 2);
+
+        client.client.released.store(true, Ordering::SeqCst);
+        for (fut, expected) in [
+            (&mut a, "http://a.test/ 0"),
+            (&mut b, "http://a.test/ 0"),
+            (&mut c, "http://c.test/ 1"),
+        ] {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(Ok(output)) => assert_eq!(output, expected),
+                ret => panic!("{ret:?}"),
+            }
+        }
+        assert!(client.flights.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_abandoned() {
+        let client = CoalescingClient::new(MyClient::default());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut a = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        let mut b = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+        drop(a);
+
+        client.client.released.store(true, Ordering::SeqCst);
+        match b.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(output)) => assert_eq!(output, "http://a.test/ 1"),
+            ret => panic!("{ret:?}"),
+        }
+        assert_eq!(client.client.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_dropped_follower() {
+        let client = CoalescingClient::new(MyClient::default());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut a = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        let mut b = client.respond_endpoint(&MyEndpoint("http://a.test/"));
+        assert!(a.as_mut().poll(&mut cx).is_pending());
+        assert!(b.as_mut().poll(&mut cx).is_pending());
+        drop(b);
+
+        let flight = client
+            .flights
+            .lock()
+            .unwrap()
+            .values()
+            .next()
+            .cloned()
+            .unwrap();
+        assert!(matches!(
+            &*flight.state.lock().unwrap(),
+            FlightState::Running(wakers) if wakers.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_extensions() {
+        let client = CoalescingClient::new(MyClient::default());
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let request = || Request::get("http://a.test/").body(vec![]).unwrap();
+        let mut with_timeout = request();
+        with_timeout
+            .extensions_mut()
+            .insert(RequestTimeout(Duration::from_secs(1)));
+
+        let with_identity = |key: &[u8]| {
+            let mut request = request();
+            request.extensions_mut().insert(TlsIdentity::Pem {
+                cert: b"cert".to_vec(),
+                key: key.to_vec(),
+            });
+            request
+        };
+
+        let mut a = client.respond(request());
+        let mut b = client.respond(with_timeout);
+        let mut c = client.respond(request());
+        let mut d = client.respond(with_identity(b"a"));
+        let mut e = client.respond(with_identity(b"b"));
+        let mut f = client.respond(with_identity(b"a"));
+        for fut in [&mut a, &mut b, &mut c, &mut d, &mut e, &mut f] {
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(client.client.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(client.flights.lock().unwrap().len(), 4);
+    }
+}
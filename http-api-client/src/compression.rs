@@ -0,0 +1,236 @@
+use core::fmt;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::{CONTENT_ENCODING, CONTENT_LENGTH},
+        HeaderValue,
+    },
+    Body, ContentCoding, Request, RequestCompression, Response,
+};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+/// Compresses request bodies as asked by the [`RequestCompression`] extension,
+/// falling back to `default`.
+///
+/// Bodies that already have a `Content-Encoding` are left untouched.
+#[derive(Debug, Clone)]
+pub struct CompressionClient<C> {
+    pub client: C,
+    pub default: Option<RequestCompression>,
+}
+
+impl<C> CompressionClient<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            default: None,
+        }
+    }
+
+    pub fn with_default(mut self, compression: RequestCompression) -> Self {
+        self.default = Some(compression);
+        self
+    }
+}
+
+pub fn encode(encoding: ContentCoding, body: &[u8]) -> Result<Vec<u8>, IoError> {
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+    use std::io::Write as _;
+
+    match encoding {
+        #[cfg(feature = "gzip")]
+        ContentCoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "deflate")]
+        ContentCoding::Deflate => {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "brotli")]
+        ContentCoding::Brotli => {
+            let mut buf = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut buf, 4096, 5, 22);
+                encoder.write_all(body)?;
+                encoder.flush()?;
+            }
+            Ok(buf)
+        }
+        #[cfg(feature = "zstd")]
+        ContentCoding::Zstd => zstd::stream::encode_all(body, 0),
+        #[allow(unreachable_patterns)]
+        encoding => Err(IoError::new(
+            IoErrorKind::Unsupported,
+            format!("{} codec is not enabled", encoding.as_str()),
+        )),
+    }
+}
+
+fn compress_request(
+    request: Request<Body>,
+    default: Option<RequestCompression>,
+) -> Result<Request<Body>, IoError> {
+    let Some(compression) = request
+        .extensions()
+        .get::<RequestCompression>()
+        .copied()
+        .or(default)
+    else {
+        return Ok(request);
+    };
+
+    if request.body().len() < compression.min_size
+        || request.headers().contains_key(CONTENT_ENCODING)
+    {
+        return Ok(request);
+    }
+
+    let (mut head, body) = request.into_parts();
+    let body = encode(compression.encoding, &body)?;
+
+    head.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(compression.encoding.as_str()),
+    );
+    head.headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+
+    Ok(Request::from_parts(head, body))
+}
+
+#[async_trait]
+impl<C> Client for CompressionClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = CompressionClientRespondError<C::RespondError>;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        let request = compress_request(request, self.default)
+            .map_err(CompressionClientRespondError::CompressFailed)?;
+
+        self.client
+            .respond(request)
+            .await
+            .map_err(CompressionClientRespondError::RespondFailed)
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for CompressionClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum CompressionClientRespondError<E> {
+    CompressFailed(IoError),
+    RespondFailed(E),
+}
+impl<E> fmt::Display for CompressionClientRespondError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E> std::error::Error for CompressionClientRespondError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CompressFailed(err) => Some(err),
+            Self::RespondFailed(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use super::*;
+
+    use std::io::Read as _;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::Endpoint;
+
+    #[derive(Debug)]
+    struct MyEchoClient;
+    #[async_trait]
+    impl Client for MyEchoClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let (head, body) = request.into_parts();
+            let mut res = Response::new(body);
+            *res.headers_mut() = head.headers;
+            Ok(res)
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyUploadEndpoint(usize);
+    impl Endpoint for MyUploadEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = (Option<HeaderValue>, Body);
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::new(vec![b'a'; self.0]))
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            let content_encoding = response.headers().get(CONTENT_ENCODING).cloned();
+            Ok((content_encoding, response.into_body()))
+        }
+
+        fn compression(&self) -> Option<RequestCompression> {
+            Some(RequestCompression::new(ContentCoding::Gzip, 1024))
+        }
+    }
+
+    #[test]
+    fn test_compress() {
+        let client = CompressionClient::new(MyEchoClient);
+
+        let (content_encoding, body) =
+            block_on(client.respond_endpoint(&MyUploadEndpoint(2048))).unwrap();
+        assert_eq!(content_encoding.unwrap(), "gzip");
+        let mut decoded = vec![];
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, vec![b'a'; 2048]);
+
+        let (content_encoding, body) =
+            block_on(client.respond_endpoint(&MyUploadEndpoint(512))).unwrap();
+        assert!(content_encoding.is_none());
+        assert_eq!(body.len(), 512);
+    }
+}
@@ -0,0 +1,660 @@
+use core::{fmt, time::Duration};
+use std::{
+    collections::BTreeMap,
+    env,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+use http_api_client_endpoint::{
+    http::{HeaderMap, HeaderName, HeaderValue},
+    VersionPreference,
+};
+
+/// Backend-neutral client settings, taken by the `from_config` constructor of each backend.
+///
+/// With the `serde` feature it can be deserialized, e.g. from TOML, with durations given
+/// as seconds or strings like `"500ms"`, `"5s"` and `"1m"`, and missing fields taking their defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ClientConfig {
+    #[cfg_attr(feature = "serde", serde(with = "serde_duration"))]
+    pub connect_timeout: Option<Duration>,
+    /// Total timeout of a request, the [`RequestTimeout`](crate::RequestTimeout) extension overrides it.
+    #[cfg_attr(feature = "serde", serde(with = "serde_duration"))]
+    pub timeout: Option<Duration>,
+    pub user_agent: Option<String>,
+    pub default_headers: BTreeMap<String, String>,
+    /// PEM files with root certificates to trust in addition to the built-in ones.
+    pub root_certificates: Vec<PathBuf>,
+    /// `false` trusts only `root_certificates`.
+    pub built_in_root_certificates: bool,
+    /// Client certificate presented for mutual TLS.
+    pub identity: Option<IdentityConfig>,
+    pub min_tls_version: Option<TlsVersion>,
+    /// Base64 SHA-256 hashes of the server's SubjectPublicKeyInfo, optionally prefixed with `sha256//` as in curl.
+    /// When not empty, the leaf certificate must match one of them, on top of the usual verification.
+    /// Intermediate and root certificates are never checked against the pins.
+    pub pinned_public_keys: Vec<String>,
+    /// `None` negotiates the version.
+    pub http_version: Option<VersionPreference>,
+    pub pool_max_idle_per_host: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_duration"))]
+    pub pool_idle_timeout: Option<Duration>,
+    pub max_response_body_size: Option<usize>,
+    /// `None` keeps the backend's own proxy handling.
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Some(Duration::from_secs(5)),
+            timeout: Some(Duration::from_secs(30)),
+            user_agent: None,
+            default_headers: BTreeMap::new(),
+            root_certificates: vec![],
+            built_in_root_certificates: true,
+            identity: None,
+            min_tls_version: None,
+            pinned_public_keys: vec![],
+            http_version: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            max_response_body_size: None,
+            proxy: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_default_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.default_headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_root_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_certificates.push(path.into());
+        self
+    }
+
+    pub fn without_built_in_root_certificates(mut self) -> Self {
+        self.built_in_root_certificates = false;
+        self
+    }
+
+    pub fn with_identity(mut self, identity: IdentityConfig) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    pub fn with_min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    pub fn with_pinned_public_key(mut self, pin: impl Into<String>) -> Self {
+        self.pinned_public_keys.push(pin.into());
+        self
+    }
+
+    pub fn with_http_version(mut self, version: VersionPreference) -> Self {
+        self.http_version = Some(version);
+        self
+    }
+
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn with_pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Overrides the defaults with `{prefix}_CONNECT_TIMEOUT`, `{prefix}_TIMEOUT`, `{prefix}_USER_AGENT`,
+    /// `{prefix}_HEADER_{NAME}` (underscores in the name become dashes),
+    /// `{prefix}_ROOT_CERTIFICATES` (a list of paths like `PATH`), `{prefix}_BUILT_IN_ROOT_CERTIFICATES` (`true` or `false`),
+    /// `{prefix}_MIN_TLS_VERSION` (`1.2` or `1.3`), `{prefix}_PINNED_PUBLIC_KEYS` (comma separated),
+    /// `{prefix}_HTTP_VERSION` (`http1_only` or `http2_prior_knowledge`), `{prefix}_POOL_MAX_IDLE_PER_HOST`,
+    /// `{prefix}_POOL_IDLE_TIMEOUT`, `{prefix}_MAX_RESPONSE_BODY_SIZE`,
+    /// `{prefix}_PROXY` (used for all schemes) and `{prefix}_NO_PROXY`.
+    ///
+    /// Any other `{prefix}_*` variable is an error, so that a misspelled one isn't ignored.
+    pub fn from_env(prefix: &str) -> Result<Self, FromEnvError> {
+        Self::from_vars(prefix, env_vars())
+    }
+
+    /// Like [`from_env`](Self::from_env), reading the variables from `vars` instead.
+    pub fn from_vars<I, K, V>(prefix: &str, vars: I) -> Result<Self, FromEnvError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut config = Self::default();
+
+        for (name, value) in vars {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            let Some(key) = name.strip_prefix(prefix).and_then(|x| x.strip_prefix('_')) else {
+                continue;
+            };
+            let invalid = || FromEnvError::InvalidValue {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            };
+
+            match key {
+                "CONNECT_TIMEOUT" => {
+                    config.connect_timeout = Some(parse_duration(value).ok_or_else(invalid)?)
+                }
+                "TIMEOUT" => config.timeout = Some(parse_duration(value).ok_or_else(invalid)?),
+                "USER_AGENT" => config.user_agent = Some(value.to_owned()),
+                "ROOT_CERTIFICATES" => config.root_certificates = env::split_paths(value).collect(),
+                "BUILT_IN_ROOT_CERTIFICATES" => {
+                    config.built_in_root_certificates = value.parse().map_err(|_| invalid())?
+                }
+                "MIN_TLS_VERSION" => {
+                    config.min_tls_version = Some(match value {
+                        "1.2" => TlsVersion::Tls1_2,
+                        "1.3" => TlsVersion::Tls1_3,
+                        _ => return Err(invalid()),
+                    })
+                }
+                "PINNED_PUBLIC_KEYS" => {
+                    config.pinned_public_keys = value
+                        .split(',')
+                        .map(|x| x.trim().to_owned())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                }
+                "HTTP_VERSION" => {
+                    config.http_version = Some(match value {
+                        "http1_only" => VersionPreference::Http1Only,
+                        "http2_prior_knowledge" => VersionPreference::Http2PriorKnowledge,
+                        _ => return Err(invalid()),
+                    })
+                }
+                "POOL_MAX_IDLE_PER_HOST" => {
+                    config.pool_max_idle_per_host = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "POOL_IDLE_TIMEOUT" => {
+                    config.pool_idle_timeout = Some(parse_duration(value).ok_or_else(invalid)?)
+                }
+                "MAX_RESPONSE_BODY_SIZE" => {
+                    config.max_response_body_size = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "PROXY" => {
+                    let proxy_config = config.proxy.get_or_insert_with(Default::default);
+                    proxy_config.http = Some(Proxy::new(value));
+                    proxy_config.https = Some(Proxy::new(value));
+                }
+                "NO_PROXY" => {
+                    config.proxy.get_or_insert_with(Default::default).no_proxy = value
+                        .split(',')
+                        .map(|x| x.trim().to_owned())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                }
+                key => match key.strip_prefix("HEADER_") {
+                    Some(header_name) => {
+                        config.default_headers.insert(
+                            header_name.replace('_', "-").to_ascii_lowercase(),
+                            value.to_owned(),
+                        );
+                    }
+                    None => return Err(FromEnvError::UnknownName(name.to_owned())),
+                },
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// `default_headers` as a [`HeaderMap`], without the user agent.
+    pub fn header_map<E>(&self) -> Result<HeaderMap, FromConfigError<E>> {
+        let mut headers = HeaderMap::with_capacity(self.default_headers.len());
+        for (name, value) in &self.default_headers {
+            let invalid = || FromConfigError::InvalidHeader(name.to_owned());
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                HeaderValue::from_str(value).map_err(|_| invalid())?,
+            );
+        }
+        Ok(headers)
+    }
+
+    /// The contents of the `root_certificates` files.
+    pub fn read_root_certificates<E>(&self) -> Result<Vec<Vec<u8>>, FromConfigError<E>> {
+        self.root_certificates
+            .iter()
+            .map(|path| read_file(path))
+            .collect()
+    }
+}
+
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+}
+
+pub(crate) fn read_file<E>(path: &Path) -> Result<Vec<u8>, FromConfigError<E>> {
+    std::fs::read(path).map_err(|err| FromConfigError::ReadFileFailed(path.to_owned(), err))
+}
+
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let number = number.trim().parse::<f64>().ok()?;
+    let secs = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+#[cfg(feature = "serde")]
+mod serde_duration {
+    use core::time::Duration;
+
+    use serde::{de::Error as _, Deserialize as _, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) if value.subsec_nanos() == 0 => {
+                serializer.serialize_some(&format!("{}s", value.as_secs()))
+            }
+            Some(value) => serializer.serialize_some(&format!("{}ms", value.as_millis())),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Secs(f64),
+        Str(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<Raw>::deserialize(deserializer)?
+            .map(|raw| match raw {
+                Raw::Secs(secs) => Duration::try_from_secs_f64(secs).map_err(D::Error::custom),
+                Raw::Str(s) => super::parse_duration(&s)
+                    .ok_or_else(|| D::Error::custom(format!("invalid duration {s:?}"))),
+            })
+            .transpose()
+    }
+}
+
+//
+/// Why a backend couldn't be built from a [`ClientConfig`].
+#[derive(Debug)]
+pub enum FromConfigError<E> {
+    InvalidHeader(String),
+    ReadFileFailed(PathBuf, IoError),
+    InvalidCertificate(PathBuf),
+    InvalidPinnedPublicKey(String),
+    InvalidTlsConfig(String),
+    /// The backend has no equivalent of the named setting.
+    Unsupported(&'static str),
+    BuildFailed(E),
+}
+impl<E> fmt::Display for FromConfigError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E> std::error::Error for FromConfigError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadFileFailed(_, err) => Some(err),
+            Self::BuildFailed(err) => Some(err),
+            Self::InvalidHeader(_)
+            | Self::InvalidCertificate(_)
+            | Self::InvalidPinnedPublicKey(_)
+            | Self::InvalidTlsConfig(_)
+            | Self::Unsupported(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromEnvError {
+    InvalidValue { name: String, value: String },
+    UnknownName(String),
+}
+impl fmt::Display for FromEnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for FromEnvError {}
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TlsVersion {
+    #[cfg_attr(feature = "serde", serde(rename = "1.2"))]
+    Tls1_2,
+    #[cfg_attr(feature = "serde", serde(rename = "1.3"))]
+    Tls1_3,
+}
+
+//
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum IdentityConfig {
+    /// PEM files with the certificate chain and its private key.
+    Pem { cert: PathBuf, key: PathBuf },
+    /// DER encoded PKCS#12 archive.
+    Pkcs12 { path: PathBuf, password: String },
+}
+
+impl core::fmt::Debug for IdentityConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Pem { cert, key } => f
+                .debug_struct("Pem")
+                .field("cert", cert)
+                .field("key", key)
+                .finish(),
+            Self::Pkcs12 { path, .. } => f
+                .debug_struct("Pkcs12")
+                .field("path", path)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+//
+/// Which proxy to use for `http://` and `https://` targets, an empty config disables proxying.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ProxyConfig {
+    pub http: Option<Proxy>,
+    pub https: Option<Proxy>,
+    /// Hosts to connect to directly, in the `NO_PROXY` format:
+    /// `example.com` also matches its subdomains, `*` matches everything, IP addresses and CIDR blocks are allowed.
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    pub fn all(proxy: Proxy) -> Self {
+        Self {
+            http: Some(proxy.clone()),
+            https: Some(proxy),
+            no_proxy: vec![],
+        }
+    }
+
+    /// Reads `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`, preferring the lowercase variants.
+    pub fn from_env() -> Self {
+        Self::from_vars(env_vars())
+    }
+
+    /// Like [`from_env`](Self::from_env), reading the variables from `vars` instead.
+    pub fn from_vars<I, K, V>(vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let vars = vars
+            .into_iter()
+            .map(|(name, value)| (name.as_ref().to_owned(), value.as_ref().to_owned()))
+            .collect::<BTreeMap<_, _>>();
+        let var = |name: &str| {
+            [name.to_ascii_lowercase(), name.to_owned()]
+                .iter()
+                .filter_map(|x| vars.get(x))
+                .find(|x| !x.trim().is_empty())
+                .cloned()
+        };
+
+        let all = var("ALL_PROXY");
+        Self {
+            http: var("HTTP_PROXY").or_else(|| all.clone()).map(Proxy::new),
+            https: var("HTTPS_PROXY").or(all).map(Proxy::new),
+            no_proxy: var("NO_PROXY")
+                .map(|x| {
+                    x.split(',')
+                        .map(|x| x.trim().to_owned())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn with_no_proxy(mut self, host: impl Into<String>) -> Self {
+        self.no_proxy.push(host.into());
+        self
+    }
+
+    /// The proxy for a target with the given scheme, not taking `no_proxy` into account.
+    pub fn for_scheme(&self, scheme: &str) -> Option<&Proxy> {
+        match scheme {
+            "https" => self.https.as_ref(),
+            _ => self.http.as_ref(),
+        }
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proxy {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` (names resolved by the proxy) URL.
+    pub url: String,
+    /// Takes precedence over credentials embedded in `url`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub auth: Option<ProxyAuth>,
+}
+
+impl Proxy {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            auth: None,
+        }
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some(ProxyAuth {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl core::fmt::Debug for ProxyAuth {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ProxyAuth")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env() {
+        let vars = BTreeMap::from([
+            ("HTTPS_PROXY", "http://upper:3128"),
+            ("https_proxy", "http://lower:3128"),
+            ("ALL_PROXY", "socks5://all:1080"),
+            ("NO_PROXY", "localhost, .internal,,"),
+        ]);
+
+        let config = ProxyConfig::from_vars(&vars);
+        assert_eq!(config.https, Some(Proxy::new("http://lower:3128")));
+        assert_eq!(config.http, Some(Proxy::new("socks5://all:1080")));
+        assert_eq!(config.no_proxy, vec!["localhost", ".internal"]);
+        assert_eq!(config.for_scheme("https").unwrap().url, "http://lower:3128");
+    }
+
+    #[test]
+    fn test_client_config_from_env() {
+        let mut vars = BTreeMap::from([
+            ("MY_API_TIMEOUT", "1.5"),
+            ("MY_API_POOL_IDLE_TIMEOUT", "500ms"),
+            ("MY_API_HEADER_X_API_VERSION", "2"),
+            ("MY_API_HTTP_VERSION", "http1_only"),
+            ("MY_API_PROXY", "http://proxy:3128"),
+            ("MY_API_MIN_TLS_VERSION", "1.3"),
+            ("MY_API_BUILT_IN_ROOT_CERTIFICATES", "false"),
+            ("OTHER_API_TIMEOUT", "invalid"),
+        ]);
+
+        let config = ClientConfig::from_vars("MY_API", &vars).unwrap();
+        assert_eq!(config.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.pool_idle_timeout, Some(Duration::from_millis(500)));
+        assert_eq!(config.default_headers["x-api-version"], "2");
+        assert_eq!(config.http_version, Some(VersionPreference::Http1Only));
+        assert_eq!(
+            config.proxy,
+            Some(ProxyConfig::all(Proxy::new("http://proxy:3128")))
+        );
+        assert_eq!(config.min_tls_version, Some(TlsVersion::Tls1_3));
+        assert!(!config.built_in_root_certificates);
+
+        vars.insert("MY_API_MAX_RESPONSE_BODY_SIZE", "1MB");
+        assert_eq!(
+            ClientConfig::from_vars("MY_API", &vars).unwrap_err(),
+            FromEnvError::InvalidValue {
+                name: "MY_API_MAX_RESPONSE_BODY_SIZE".into(),
+                value: "1MB".into()
+            }
+        );
+
+        vars.remove("MY_API_MAX_RESPONSE_BODY_SIZE");
+        vars.insert("MY_API_TIMEOUTS", "1");
+        assert_eq!(
+            ClientConfig::from_vars("MY_API", &vars).unwrap_err(),
+            FromEnvError::UnknownName("MY_API_TIMEOUTS".into())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize() {
+        let config: ClientConfig = toml::from_str(
+            r#"
+            timeout = "10s"
+            connect_timeout = 2
+            user_agent = "my-api/1.0"
+            http_version = "http2_prior_knowledge"
+            max_response_body_size = 1048576
+            min_tls_version = "1.2"
+            pinned_public_keys = ["sha256//AAAA"]
+            identity = { pem = { cert = "client.pem", key = "client.key" } }
+
+            [default_headers]
+            x-api-version = "2"
+
+            [proxy]
+            https = { url = "socks5h://proxy:1080", auth = { username = "u", password = "p" } }
+            no_proxy = ["localhost"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ClientConfig::new()
+                .with_timeout(Duration::from_secs(10))
+                .with_connect_timeout(Duration::from_secs(2))
+                .with_user_agent("my-api/1.0")
+                .with_http_version(VersionPreference::Http2PriorKnowledge)
+                .with_max_response_body_size(1048576)
+                .with_min_tls_version(TlsVersion::Tls1_2)
+                .with_pinned_public_key("sha256//AAAA")
+                .with_identity(IdentityConfig::Pem {
+                    cert: "client.pem".into(),
+                    key: "client.key".into()
+                })
+                .with_default_header("x-api-version", "2")
+                .with_proxy(ProxyConfig {
+                    http: None,
+                    https: Some(Proxy::new("socks5h://proxy:1080").with_auth("u", "p")),
+                    no_proxy: vec!["localhost".into()],
+                })
+        );
+
+        let config: ClientConfig = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config.timeout, Some(Duration::from_secs(10)));
+    }
+}
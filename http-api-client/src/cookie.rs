@@ -0,0 +1,637 @@
+use core::time::Duration;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error as IoError, Write as _},
+    net::IpAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderValue, Uri,
+    },
+    Body, Request, Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+/// A cookie as stored by [`CookieJar`], following the storage model of RFC 6265 section 5.3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    /// Set when the cookie had no `Domain` attribute, so it is only sent back to `domain` itself.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Seconds since the Unix epoch, `None` for a session cookie.
+    pub expires_at: Option<u64>,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header value received in response to a request to `request_uri`.
+    ///
+    /// Returns `None` for cookies a user agent has to ignore.
+    pub fn parse(request_uri: &Uri, set_cookie: &str) -> Option<Self> {
+        Self::parse_at(request_uri, set_cookie, SystemTime::now())
+    }
+
+    fn parse_at(request_uri: &Uri, set_cookie: &str, now: SystemTime) -> Option<Self> {
+        let host = request_uri.host()?.to_ascii_lowercase();
+
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut expires_at = None;
+        let mut max_age = None;
+        let mut domain = None;
+        let mut path = None;
+        let mut secure = false;
+        let mut http_only = false;
+        for attribute in attributes {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => {
+                    if let Some(secs) = parse_cookie_date(value) {
+                        expires_at = Some(secs);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(secs) = value.parse::<i64>() {
+                        max_age = Some(match u64::try_from(secs) {
+                            Ok(secs) if secs > 0 => unix_secs(now) + secs,
+                            _ => 0,
+                        });
+                    }
+                }
+                "domain" if !value.is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+                }
+                "path" if value.starts_with('/') => path = Some(value.to_owned()),
+                "secure" => secure = true,
+                "httponly" => http_only = true,
+                _ => {}
+            }
+        }
+
+        let (domain, host_only) = match domain {
+            Some(domain) => {
+                // Without a public suffix list, at least refuse single label domains like `com`.
+                if !domain_match(&host, &domain) || (domain != host && !domain.contains('.')) {
+                    return None;
+                }
+                (domain, false)
+            }
+            None => (host, true),
+        };
+
+        Some(Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            domain,
+            host_only,
+            path: path.unwrap_or_else(|| default_path(request_uri.path()).to_owned()),
+            secure,
+            http_only,
+            expires_at: max_age.or(expires_at),
+        })
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(SystemTime::now())
+    }
+
+    fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= unix_secs(now))
+    }
+
+    /// Whether the cookie should be sent along with a request to `uri`, RFC 6265 section 5.4.
+    pub fn matches(&self, uri: &Uri) -> bool {
+        let Some(host) = uri.host() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+
+        let domain_matched = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+
+        domain_matched
+            && path_match(uri.path(), &self.path)
+            && (!self.secure || uri.scheme_str() == Some("https"))
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+// RFC 6265 section 5.1.1, returning seconds since the Unix epoch (0 for earlier dates).
+fn parse_cookie_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let is_delimiter = |c: char| matches!(c, '\x09' | '\x20'..='\x2F' | '\x3B'..='\x40' | '\x5B'..='\x60' | '\x7B'..='\x7E');
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in value.split(is_delimiter).filter(|x| !x.is_empty()) {
+        if time.is_none() {
+            if let Some(x) = parse_cookie_time(token) {
+                time = Some(x);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some((x, _)) = leading_digits(token, 1, 2) {
+                day = Some(x);
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).map(str::to_ascii_lowercase);
+            if let Some(i) = MONTHS.iter().position(|x| prefix.as_deref() == Some(*x)) {
+                month = Some(i as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some((x, _)) = leading_digits(token, 2, 4) {
+                year = Some(x);
+                continue;
+            }
+        }
+    }
+
+    let ((hour, minute, second), day, month, mut year) = (time?, day?, month?, year?);
+    match year {
+        70..=99 => year += 1900,
+        0..=69 => year += 2000,
+        _ => {}
+    }
+    if year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if is_leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+
+    // Days since 1970-01-01 of the proleptic Gregorian calendar, shifting years to start in March.
+    let (year, month) = (
+        i64::from(year) - i64::from(month <= 2),
+        i64::from((month + 9) % 12),
+    );
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let secs = days * 86400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+    Some(u64::try_from(secs).unwrap_or(0))
+}
+
+// `hms-time`, `1*2DIGIT ":" 1*2DIGIT ":" 1*2DIGIT ( non-digit *OCTET )`
+fn parse_cookie_time(token: &str) -> Option<(u32, u32, u32)> {
+    let (hour, rest) = leading_digits(token, 1, 2)?;
+    let (minute, rest) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    let (second, _) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+// `min*maxDIGIT ( non-digit *OCTET )`, returning the number and what follows it.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<(u32, &str)> {
+    let len = token
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(token.len());
+    if !(min..=max).contains(&len) {
+        return None;
+    }
+    Some((token[..len].parse().ok()?, &token[len..]))
+}
+
+// RFC 6265 section 5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.trim_matches(['[', ']']).parse::<IpAddr>().is_err())
+}
+
+// RFC 6265 section 5.1.4
+fn default_path(request_path: &str) -> &str {
+    match request_path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &request_path[..i],
+    }
+}
+
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+//
+/// Thread safe cookie store, shared between clients through an `Arc`.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the cookie, replacing the one with the same name, domain and path.
+    /// An already expired cookie removes that one instead.
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().expect("cookie jar poisoned");
+        cookies.retain(|x| {
+            !(x.name == cookie.name && x.domain == cookie.domain && x.path == cookie.path)
+        });
+        if !cookie.is_expired() {
+            cookies.push(cookie);
+        }
+    }
+
+    pub fn store_response_cookies(&self, request_uri: &Uri, response: &Response<Body>) {
+        for set_cookie in response.headers().get_all(SET_COOKIE) {
+            if let Some(cookie) = set_cookie
+                .to_str()
+                .ok()
+                .and_then(|x| Cookie::parse(request_uri, x))
+            {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// The `Cookie` header value for a request to `uri`, longer paths first.
+    pub fn cookie_header(&self, uri: &Uri) -> Option<String> {
+        let mut cookies = self.cookies();
+        cookies.retain(|x| x.matches(uri));
+        if cookies.is_empty() {
+            return None;
+        }
+        cookies.sort_by_key(|x| core::cmp::Reverse(x.path.len()));
+
+        Some(
+            cookies
+                .iter()
+                .map(|x| format!("{}={}", x.name, x.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// All cookies that have not expired yet.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let mut cookies = self.cookies.lock().expect("cookie jar poisoned");
+        cookies.retain(|x| !x.is_expired_at(now));
+        cookies.to_owned()
+    }
+
+    pub fn clear(&self) {
+        self.cookies.lock().expect("cookie jar poisoned").clear();
+    }
+
+    /// Reads a jar written by [`CookieJar::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        let cookies: Vec<Cookie> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        let jar = Self::new();
+        for cookie in cookies {
+            jar.insert(cookie);
+        }
+        Ok(jar)
+    }
+
+    /// Writes all cookies that have not expired yet, session cookies included, as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), IoError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.cookies())?;
+        writer.flush()
+    }
+}
+
+//
+/// Sends the cookies of `jar` along with each request and stores the ones the responses set.
+///
+/// A `Cookie` header set by the endpoint is left untouched, one injected by this client is
+/// recomputed on each call.
+/// Wrap it in a [`RedirectClient`](crate::RedirectClient) for the jar to see every hop.
+#[derive(Debug, Clone)]
+pub struct CookieClient<C> {
+    pub client: C,
+    pub jar: Arc<CookieJar>,
+}
+
+/// The `Cookie` header value injected by [`CookieClient`], kept to tell it from one set by the
+/// endpoint when the request comes back, e.g. for the next hop of a redirect.
+#[derive(Debug, Clone)]
+struct InjectedCookie(HeaderValue);
+
+impl<C> CookieClient<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            jar: Default::default(),
+        }
+    }
+
+    pub fn with_jar(mut self, jar: Arc<CookieJar>) -> Self {
+        self.jar = jar;
+        self
+    }
+}
+
+#[async_trait]
+impl<C> Client for CookieClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = C::RespondError;
+
+    async fn respond(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, Self::RespondError> {
+        let uri = request.uri().to_owned();
+
+        let injected = request.extensions_mut().remove::<InjectedCookie>();
+        let own = match request.headers().get(COOKIE) {
+            Some(value) => injected.is_some_and(|InjectedCookie(x)| x == value),
+            None => true,
+        };
+        if own {
+            request.headers_mut().remove(COOKIE);
+            if let Some(value) = self
+                .jar
+                .cookie_header(&uri)
+                .and_then(|x| HeaderValue::from_str(&x).ok())
+            {
+                request.headers_mut().insert(COOKIE, value.to_owned());
+                request.extensions_mut().insert(InjectedCookie(value));
+            }
+        }
+
+        let res = self.client.respond(request).await?;
+        self.jar.store_response_cookies(&uri, &res);
+
+        Ok(res)
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for CookieClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::{
+        http::{header::LOCATION, StatusCode},
+        Endpoint,
+    };
+
+    use crate::RedirectClient;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        let cookie = Cookie::parse_at(
+            &uri("https://www.example.com/a/b"),
+            "sid=abc; Domain=.Example.com; Secure; HttpOnly; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            cookie,
+            Cookie {
+                name: "sid".into(),
+                value: "abc".into(),
+                domain: "example.com".into(),
+                host_only: false,
+                path: "/a".into(),
+                secure: true,
+                http_only: true,
+                expires_at: Some(1_000_000_060),
+            }
+        );
+        assert!(cookie.matches(&uri("https://api.example.com/a/c")));
+        assert!(cookie.matches(&uri("https://example.com/a")));
+        assert!(!cookie.matches(&uri("http://example.com/a")));
+        assert!(!cookie.matches(&uri("https://example.com/ab")));
+        assert!(!cookie.matches(&uri("https://badexample.com/a")));
+
+        let cookie = Cookie::parse_at(
+            &uri("http://example.com/"),
+            "a=1; Path=/x; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            now,
+        )
+        .unwrap();
+        assert!(cookie.host_only);
+        assert_eq!(cookie.expires_at, Some(1445412480));
+        assert!(!cookie.matches(&uri("http://www.example.com/x")));
+
+        for (expires, expected) in [
+            ("Wed, 21 Oct 2015 07:28:00 GMT", Some(1445412480)),
+            ("Thu, 01-Jan-1970 00:00:01 GMT", Some(1)),
+            ("Wednesday, 21-Oct-15 07:28:00 GMT", Some(1445412480)),
+            ("Wed Oct 21 07:28:00 2015", Some(1445412480)),
+            ("21 October 99 7:28:0", Some(940490880)),
+            ("Sat, 01 Jan 1601 00:00:00 GMT", Some(0)),
+            ("Thu, 29 Feb 2024 00:00:00 GMT", Some(1709164800)),
+            ("Thu, 30 Feb 2024 00:00:00 GMT", None),
+            ("Wed, 21 Oct 2015 24:00:00 GMT", None),
+            ("Wed, 21 Oct 2015 GMT", None),
+        ] {
+            let cookie = Cookie::parse_at(
+                &uri("http://example.com/"),
+                &format!("a=1; Expires={expires}"),
+                now,
+            )
+            .unwrap();
+            assert_eq!(cookie.expires_at, expected, "{expires}");
+        }
+
+        assert!(
+            Cookie::parse_at(&uri("http://example.com/"), "a=1; Domain=other.com", now).is_none()
+        );
+        assert!(Cookie::parse_at(&uri("http://example.com/"), "a=1; Domain=com", now).is_none());
+        assert!(Cookie::parse_at(&uri("http://example.com/"), "=1", now).is_none());
+        assert!(Cookie::parse_at(&uri("http://example.com/"), "novalue", now).is_none());
+    }
+
+    #[test]
+    fn test_jar() {
+        let jar = CookieJar::new();
+        let request_uri = uri("http://example.com/");
+        jar.insert(Cookie::parse(&request_uri, "a=1").unwrap());
+        jar.insert(Cookie::parse(&request_uri, "b=2; Path=/x").unwrap());
+        assert_eq!(
+            jar.cookie_header(&uri("http://example.com/x/y")).unwrap(),
+            "b=2; a=1"
+        );
+
+        jar.insert(Cookie::parse(&request_uri, "a=3").unwrap());
+        jar.insert(Cookie::parse(&request_uri, "b=; Path=/x; Max-Age=0").unwrap());
+        assert_eq!(
+            jar.cookie_header(&uri("http://example.com/x/y")).unwrap(),
+            "a=3"
+        );
+
+        let path = std::env::temp_dir().join(format!("cookies-{}.json", uuid::Uuid::new_v4()));
+        jar.save(&path).unwrap();
+        let loaded = CookieJar::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.cookies(), jar.cookies());
+    }
+
+    #[derive(Debug)]
+    struct MyLoginClient;
+    #[async_trait]
+    impl Client for MyLoginClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let mut res = Response::new(
+                request
+                    .headers()
+                    .get(COOKIE)
+                    .map(|x| x.as_bytes().to_vec())
+                    .unwrap_or_default(),
+            );
+            if request.uri().path() == "/login" {
+                *res.status_mut() = StatusCode::FOUND;
+                res.headers_mut()
+                    .insert(SET_COOKIE, HeaderValue::from_static("sid=abc; Path=/"));
+                res.headers_mut()
+                    .insert(LOCATION, HeaderValue::from_static("/me"));
+            }
+            Ok(res)
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint(&'static str);
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = Body;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::get(self.0).body(vec![]).unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(response.into_body())
+        }
+    }
+
+    #[test]
+    fn test_client() {
+        let client = CookieClient::new(MyLoginClient);
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("http://example.com/login"))).unwrap(),
+            b""
+        );
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("http://example.com/me"))).unwrap(),
+            b"sid=abc"
+        );
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("http://other.com/me"))).unwrap(),
+            b""
+        );
+
+        let mut request = Request::get("http://example.com/me")
+            .header(COOKIE, "sid=old")
+            .body(vec![])
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(InjectedCookie(HeaderValue::from_static("sid=old")));
+        assert_eq!(
+            block_on(client.respond(request)).unwrap().body(),
+            b"sid=abc"
+        );
+
+        let request = Request::get("http://example.com/me")
+            .header(COOKIE, "sid=mine")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(
+            block_on(client.respond(request)).unwrap().body(),
+            b"sid=mine"
+        );
+    }
+
+    #[test]
+    fn test_redirect_client() {
+        let jar = Arc::new(CookieJar::new());
+        jar.insert(Cookie::parse(&uri("http://example.com/"), "theme=dark").unwrap());
+
+        let client = RedirectClient::new(CookieClient::new(MyLoginClient).with_jar(jar));
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("http://example.com/login"))).unwrap(),
+            b"theme=dark; sid=abc"
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use core::fmt;
+use std::io::{Error as IoError, Read};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH},
+        HeaderValue, Method,
+    },
+    Body, Request, Response,
+};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 32 * 1024 * 1024;
+
+/// Sets `Accept-Encoding` and decodes `Content-Encoding` of responses, for clients
+/// that don't do it themselves.
+///
+/// Encodings without an enabled codec are passed through untouched.
+#[derive(Debug, Clone)]
+pub struct DecompressionClient<C> {
+    pub client: C,
+    pub max_decompressed_size: usize,
+}
+
+impl<C> DecompressionClient<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+}
+
+pub fn accept_encoding() -> &'static str {
+    const ENCODINGS: &[(bool, &str)] = &[
+        (cfg!(feature = "gzip"), "gzip"),
+        (cfg!(feature = "deflate"), "deflate"),
+        (cfg!(feature = "brotli"), "br"),
+        (cfg!(feature = "zstd"), "zstd"),
+    ];
+    static ACCEPT_ENCODING_VALUE: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+    ACCEPT_ENCODING_VALUE.get_or_init(|| {
+        ENCODINGS
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, x)| *x)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+fn decode(
+    encoding: &str,
+    body: &[u8],
+    max_size: usize,
+) -> Option<Result<Vec<u8>, DecompressionError>> {
+    let reader: Box<dyn Read + '_> = match encoding {
+        #[cfg(feature = "gzip")]
+        "gzip" | "x-gzip" => Box::new(flate2::read::MultiGzDecoder::new(body)),
+        // Servers send both zlib-wrapped (as specified) and raw deflate.
+        #[cfg(feature = "deflate")]
+        "deflate" if is_zlib_header(body) => Box::new(flate2::read::ZlibDecoder::new(body)),
+        #[cfg(feature = "deflate")]
+        "deflate" => Box::new(flate2::read::DeflateDecoder::new(body)),
+        #[cfg(feature = "brotli")]
+        "br" => Box::new(brotli::Decompressor::new(body, 4096)),
+        #[cfg(feature = "zstd")]
+        "zstd" => match zstd::stream::read::Decoder::with_buffer(body) {
+            Ok(x) => Box::new(x),
+            Err(err) => return Some(Err(DecompressionError::DecodeFailed(err))),
+        },
+        _ => return None,
+    };
+
+    let mut buf = Vec::new();
+    Some(
+        match reader.take(max_size as u64 + 1).read_to_end(&mut buf) {
+            Ok(_) if buf.len() > max_size => Err(DecompressionError::TooLarge { limit: max_size }),
+            Ok(_) => Ok(buf),
+            Err(err) => Err(DecompressionError::DecodeFailed(err)),
+        },
+    )
+}
+
+#[cfg(feature = "deflate")]
+fn is_zlib_header(body: &[u8]) -> bool {
+    match body {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+fn decompress_response(
+    method: &Method,
+    response: Response<Body>,
+    max_size: usize,
+) -> Result<Response<Body>, DecompressionError> {
+    // Nothing to decode, and `Content-Length` describes the body that would have been sent.
+    if method == Method::HEAD || response.body().is_empty() {
+        return Ok(response);
+    }
+
+    let Some(content_encoding) = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.to_ascii_lowercase())
+    else {
+        return Ok(response);
+    };
+
+    let (mut head, mut body) = response.into_parts();
+
+    // Codings are listed in the order they were applied.
+    let mut encodings = content_encoding
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && *x != "identity")
+        .collect::<Vec<_>>();
+    while let Some(encoding) = encodings.last() {
+        match decode(encoding, &body, max_size) {
+            Some(ret) => body = ret?,
+            None => break,
+        }
+        encodings.pop();
+    }
+
+    if encodings.is_empty() {
+        head.headers.remove(CONTENT_ENCODING);
+    } else {
+        head.headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_str(&encodings.join(", ")).expect("from a valid header value"),
+        );
+    }
+    head.headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+
+    Ok(Response::from_parts(head, body))
+}
+
+#[async_trait]
+impl<C> Client for DecompressionClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = DecompressionClientRespondError<C::RespondError>;
+
+    async fn respond(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, Self::RespondError> {
+        if !request.headers().contains_key(ACCEPT_ENCODING) && !accept_encoding().is_empty() {
+            request
+                .headers_mut()
+                .insert(ACCEPT_ENCODING, HeaderValue::from_static(accept_encoding()));
+        }
+
+        let method = request.method().to_owned();
+
+        let response = self
+            .client
+            .respond(request)
+            .await
+            .map_err(DecompressionClientRespondError::RespondFailed)?;
+
+        decompress_response(&method, response, self.max_decompressed_size)
+            .map_err(DecompressionClientRespondError::DecompressFailed)
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for DecompressionClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum DecompressionError {
+    DecodeFailed(IoError),
+    TooLarge { limit: usize },
+}
+impl fmt::Display for DecompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for DecompressionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::DecodeFailed(err) => Some(err),
+            Self::TooLarge { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DecompressionClientRespondError<E> {
+    RespondFailed(E),
+    DecompressFailed(DecompressionError),
+}
+impl<E> fmt::Display for DecompressionClientRespondError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E> std::error::Error for DecompressionClientRespondError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::DecompressFailed(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    )
+))]
+mod tests {
+    use super::*;
+
+    use std::{io::Write as _, sync::Mutex};
+
+    use futures_executor::block_on;
+
+    #[derive(Debug, Default)]
+    struct MyEncodedClient {
+        content_encoding: &'static str,
+        body: Body,
+        accept_encoding: Mutex<Option<HeaderValue>>,
+    }
+    #[async_trait]
+    impl Client for MyEncodedClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            *self.accept_encoding.lock().unwrap() = request.headers().get(ACCEPT_ENCODING).cloned();
+            let body = if request.method() == Method::HEAD {
+                Body::new()
+            } else {
+                self.body.to_owned()
+            };
+            Ok(Response::builder()
+                .header(CONTENT_ENCODING, self.content_encoding)
+                .header(CONTENT_LENGTH, self.body.len())
+                .body(body)
+                .unwrap())
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "deflate")]
+    fn zlib(body: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "deflate")]
+    fn deflate(body: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "zstd")]
+    fn zstd(body: &[u8]) -> Vec<u8> {
+        let mut encoder = zstd::stream::write::Encoder::new(vec![], 0).unwrap();
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "brotli")]
+    fn brotli(body: &[u8]) -> Vec<u8> {
+        let mut buf = vec![];
+        brotli::CompressorWriter::new(&mut buf, 4096, 5, 22)
+            .write_all(body)
+            .unwrap();
+        buf
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decompress_gzip() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "gzip",
+            body: gzip(b"hello"),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert_eq!(res.body(), b"hello");
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "5");
+        assert_eq!(
+            client.client.accept_encoding.lock().unwrap().as_ref(),
+            Some(&HeaderValue::from_static(accept_encoding()))
+        );
+
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "x-custom, gzip",
+            body: gzip(b"hello"),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert_eq!(res.body(), b"hello");
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "x-custom");
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_decompress_brotli() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "br",
+            body: brotli(b"hello"),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert_eq!(res.body(), b"hello");
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "5");
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn test_decompress_deflate() {
+        for body in [zlib(b"hello"), deflate(b"hello")] {
+            let client = DecompressionClient::new(MyEncodedClient {
+                content_encoding: "deflate",
+                body,
+                ..Default::default()
+            });
+            let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+            assert_eq!(res.body(), b"hello");
+            assert!(res.headers().get(CONTENT_ENCODING).is_none());
+            assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "5");
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decompress_zstd() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "zstd",
+            body: zstd(b"hello"),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert_eq!(res.body(), b"hello");
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "5");
+    }
+
+    #[cfg(all(feature = "gzip", feature = "brotli"))]
+    #[test]
+    fn test_decompress_stacked() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "gzip, br",
+            body: brotli(&gzip(b"hello")),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert_eq!(res.body(), b"hello");
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_too_large() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "gzip",
+            body: gzip(&[0; 1024 * 1024]),
+            ..Default::default()
+        })
+        .with_max_decompressed_size(1024);
+        assert!(matches!(
+            block_on(client.respond(Request::new(vec![]))),
+            Err(DecompressionClientRespondError::DecompressFailed(
+                DecompressionError::TooLarge { limit: 1024 }
+            ))
+        ));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_head_and_empty_body() {
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "gzip",
+            body: gzip(b"hello"),
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::head("/").body(vec![]).unwrap())).unwrap();
+        assert!(res.body().is_empty());
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(
+            res.headers().get(CONTENT_LENGTH).unwrap(),
+            &client.client.body.len().to_string()
+        );
+
+        let client = DecompressionClient::new(MyEncodedClient {
+            content_encoding: "gzip",
+            ..Default::default()
+        });
+        let res = block_on(client.respond(Request::new(vec![]))).unwrap();
+        assert!(res.body().is_empty());
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "0");
+    }
+}
@@ -0,0 +1,614 @@
+use core::{convert::Infallible, fmt, time::Duration};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{header::HOST, uri::Authority},
+    Body, Request, Response,
+};
+
+use crate::{load_balancing::rewrite_uri, Client, ClientMetrics, Sleeper, WithSleeper};
+
+#[cfg(feature = "dns-srv")]
+pub use hickory_resolver;
+
+/// Turns a logical service name into the addresses serving it.
+#[async_trait]
+pub trait Resolver {
+    type ResolveError: std::error::Error + Send + Sync + 'static;
+
+    /// Empty when `service` isn't one this resolver knows.
+    async fn resolve(&self, service: &str) -> Result<Vec<Authority>, Self::ResolveError>;
+
+    /// The addresses with their share of the requests, all the same by default.
+    /// An address with a weight of 0 only gets requests when all of them have a weight of 0.
+    async fn resolve_weighted(
+        &self,
+        service: &str,
+    ) -> Result<Vec<(Authority, u32)>, Self::ResolveError>
+    where
+        Self: Sync,
+    {
+        Ok(self
+            .resolve(service)
+            .await?
+            .into_iter()
+            .map(|x| (x, 1))
+            .collect())
+    }
+}
+
+/// Replaces the host of each request with an address of the service it names, e.g. `http://users/v1/me`,
+/// going smooth weighted round robin over the addresses.
+///
+/// Addresses are resolved again on the first request after `refresh_interval`,
+/// the previous ones are kept when that fails. Hosts the resolver doesn't know are left as they are.
+#[derive(Debug, Clone)]
+pub struct DiscoveryClient<C, R> {
+    pub client: C,
+    pub resolver: R,
+    pub refresh_interval: Duration,
+    resolved: Arc<Mutex<HashMap<String, Resolved>>>,
+}
+
+#[derive(Debug)]
+struct Resolved {
+    authorities: Vec<(Authority, u32)>,
+    resolved_at: Instant,
+    // How far behind its share each address is, as in nginx's smooth weighted round robin.
+    current: Vec<i64>,
+}
+
+impl Resolved {
+    fn new(mut authorities: Vec<(Authority, u32)>) -> Self {
+        if authorities.iter().all(|(_, weight)| *weight == 0) {
+            authorities.iter_mut().for_each(|(_, weight)| *weight = 1);
+        }
+        Self {
+            current: vec![0; authorities.len()],
+            authorities,
+            resolved_at: Instant::now(),
+        }
+    }
+
+    fn pick(&mut self) -> Option<Authority> {
+        let mut total = 0;
+        let mut best = None;
+        for (i, (_, weight)) in self.authorities.iter().enumerate() {
+            self.current[i] += i64::from(*weight);
+            total += i64::from(*weight);
+            if best.is_none_or(|best| self.current[i] > self.current[best]) {
+                best = Some(i);
+            }
+        }
+        let best = best?;
+        self.current[best] -= total;
+        Some(self.authorities[best].0.to_owned())
+    }
+}
+
+impl<C, R> DiscoveryClient<C, R> {
+    pub fn new(client: C, resolver: R) -> Self {
+        Self {
+            client,
+            resolver,
+            refresh_interval: Duration::from_secs(30),
+            resolved: Default::default(),
+        }
+    }
+
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    // `None` when it has to be resolved, `Some(None)` when it isn't a service.
+    fn cached(&self, service: &str, allow_stale: bool) -> Option<Option<Authority>> {
+        let mut resolved = self.resolved.lock().expect("poisoned");
+        let resolved = resolved.get_mut(service)?;
+        if !allow_stale && resolved.resolved_at.elapsed() >= self.refresh_interval {
+            return None;
+        }
+        Some(resolved.pick())
+    }
+
+    fn store(&self, service: &str, authorities: Vec<(Authority, u32)>) -> Option<Authority> {
+        let mut resolved = self.resolved.lock().expect("poisoned");
+        let mut resolved_service = Resolved::new(authorities);
+        // Carries on where it was when nothing changed, rather than starting over with the first address.
+        if let Some(prev) = resolved
+            .get(service)
+            .filter(|x| x.authorities == resolved_service.authorities)
+        {
+            resolved_service.current.clone_from(&prev.current);
+        }
+        let authority = resolved_service.pick();
+        resolved.insert(service.to_owned(), resolved_service);
+        authority
+    }
+}
+
+#[async_trait]
+impl<C, R> Client for DiscoveryClient<C, R>
+where
+    C: Client + Send + Sync,
+    R: Resolver + Send + Sync,
+{
+    type RespondError = DiscoveryClientRespondError<C::RespondError, R::ResolveError>;
+
+    async fn respond(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, Self::RespondError> {
+        if let Some(service) = request.uri().host().map(ToOwned::to_owned) {
+            let authority = match self.cached(&service, false) {
+                Some(authority) => authority,
+                None => match self.resolver.resolve_weighted(&service).await {
+                    Ok(authorities) => self.store(&service, authorities),
+                    Err(err) => self
+                        .cached(&service, true)
+                        .ok_or(DiscoveryClientRespondError::ResolveFailed(err))?,
+                },
+            };
+
+            if let Some(authority) = authority {
+                *request.uri_mut() = rewrite_uri(request.uri(), None, &authority)
+                    .ok_or(DiscoveryClientRespondError::InvalidAddress(authority))?;
+                request.headers_mut().remove(HOST);
+            }
+        }
+
+        self.client
+            .respond(request)
+            .await
+            .map_err(DiscoveryClientRespondError::RespondFailed)
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C, R> WithSleeper for DiscoveryClient<C, R>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum DiscoveryClientRespondError<E, RE> {
+    RespondFailed(E),
+    ResolveFailed(RE),
+    InvalidAddress(Authority),
+}
+impl<E, RE> fmt::Display for DiscoveryClientRespondError<E, RE>
+where
+    E: fmt::Debug,
+    RE: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E, RE> std::error::Error for DiscoveryClientRespondError<E, RE>
+where
+    E: std::error::Error + 'static,
+    RE: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::ResolveFailed(err) => Some(err),
+            Self::InvalidAddress(_) => None,
+        }
+    }
+}
+
+//
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    pub services: HashMap<String, Vec<Authority>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_service(
+        mut self,
+        service: impl Into<String>,
+        authorities: impl IntoIterator<Item = Authority>,
+    ) -> Self {
+        self.services
+            .insert(service.into(), authorities.into_iter().collect());
+        self
+    }
+}
+
+#[async_trait]
+impl Resolver for StaticResolver {
+    type ResolveError = Infallible;
+
+    async fn resolve(&self, service: &str) -> Result<Vec<Authority>, Self::ResolveError> {
+        Ok(self.services.get(service).cloned().unwrap_or_default())
+    }
+}
+
+/// Reads a file on each resolve, with a line per service like `users users-1.internal:8080 users-2.internal:8080`.
+/// Empty lines and lines starting with `#` are skipped.
+///
+/// With the `async-io` or `tokio` feature the file is read on a blocking thread pool, otherwise with
+/// blocking I/O on the task resolving, which is only meant for a small local file.
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    pub path: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Resolver for FileResolver {
+    type ResolveError = IoError;
+
+    async fn resolve(&self, service: &str) -> Result<Vec<Authority>, Self::ResolveError> {
+        let content = read_to_string(&self.path).await?;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            if words.next() != Some(service) {
+                continue;
+            }
+            return words
+                .map(|word| {
+                    word.parse()
+                        .map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+                })
+                .collect();
+        }
+        Ok(vec![])
+    }
+}
+
+/// async-fs works under any executor, so it is preferred over tokio's, which needs a tokio runtime.
+async fn read_to_string(path: &Path) -> Result<String, IoError> {
+    #[cfg(feature = "async-io")]
+    let ret = async_fs::read_to_string(path).await;
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    let ret = tokio::fs::read_to_string(path).await;
+    #[cfg(not(any(feature = "tokio", feature = "async-io")))]
+    let ret = std::fs::read_to_string(path);
+
+    ret
+}
+
+/// Looks up the SRV records of the service, as in RFC 2782: only the targets with the lowest priority
+/// are used, falling back to the next priority when none of them is usable, weighted by their weight.
+#[cfg(feature = "dns-srv")]
+#[derive(Clone)]
+pub struct DnsSrvResolver {
+    pub resolver: hickory_resolver::TokioResolver,
+    /// Prepended to the service name to make the name looked up, by default `_http._tcp.`.
+    pub prefix: String,
+}
+
+#[cfg(feature = "dns-srv")]
+impl fmt::Debug for DnsSrvResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsSrvResolver")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "dns-srv")]
+impl DnsSrvResolver {
+    pub fn new(resolver: hickory_resolver::TokioResolver) -> Self {
+        Self {
+            resolver,
+            prefix: "_http._tcp.".to_owned(),
+        }
+    }
+
+    /// With the system's DNS configuration, e.g. `/etc/resolv.conf`.
+    pub fn from_system_conf() -> Result<Self, hickory_resolver::ResolveError> {
+        Ok(Self::new(
+            hickory_resolver::TokioResolver::builder_tokio()?.build(),
+        ))
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+}
+
+#[cfg(feature = "dns-srv")]
+#[async_trait]
+impl Resolver for DnsSrvResolver {
+    type ResolveError = hickory_resolver::ResolveError;
+
+    async fn resolve(&self, service: &str) -> Result<Vec<Authority>, Self::ResolveError> {
+        Ok(self
+            .resolve_weighted(service)
+            .await?
+            .into_iter()
+            .map(|(authority, _)| authority)
+            .collect())
+    }
+
+    async fn resolve_weighted(
+        &self,
+        service: &str,
+    ) -> Result<Vec<(Authority, u32)>, Self::ResolveError> {
+        let lookup = match self
+            .resolver
+            .srv_lookup(format!("{}{}", self.prefix, service))
+            .await
+        {
+            Ok(lookup) => lookup,
+            Err(err) if err.is_no_records_found() => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        Ok(lowest_priority_group(lookup.iter().map(|x| {
+            // A target of `.` means the service isn't available there.
+            let authority = (!x.target().is_root())
+                .then(|| {
+                    format!(
+                        "{}:{}",
+                        x.target().to_utf8().trim_end_matches('.'),
+                        x.port()
+                    )
+                    .parse()
+                    .ok()
+                })
+                .flatten();
+            (x.priority(), x.weight(), authority)
+        })))
+    }
+}
+
+#[cfg(feature = "dns-srv")]
+fn lowest_priority_group(
+    records: impl IntoIterator<Item = (u16, u16, Option<Authority>)>,
+) -> Vec<(Authority, u32)> {
+    let mut records = records
+        .into_iter()
+        .filter_map(|(priority, weight, authority)| Some((priority, weight, authority?)))
+        .collect::<Vec<_>>();
+    records.sort_by_key(|(priority, _, _)| *priority);
+    let Some(lowest) = records.first().map(|(priority, _, _)| *priority) else {
+        return vec![];
+    };
+    records
+        .into_iter()
+        .take_while(|(priority, _, _)| *priority == lowest)
+        .map(|(_, weight, authority)| (authority, u32::from(weight)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::fs;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::Endpoint;
+
+    // Answers with the URI.
+    #[derive(Debug, Default)]
+    struct MyClient;
+    #[async_trait]
+    impl Client for MyClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            Ok(Response::new(request.uri().to_string().into_bytes()))
+        }
+    }
+
+    // Fails after the first resolve.
+    #[derive(Debug, Default)]
+    struct MyResolver {
+        calls: AtomicUsize,
+    }
+    #[async_trait]
+    impl Resolver for MyResolver {
+        type ResolveError = IoError;
+
+        async fn resolve(&self, _service: &str) -> Result<Vec<Authority>, Self::ResolveError> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(vec![Authority::from_static("10.0.0.1:8080")]),
+                _ => Err(IoError::other("unavailable")),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint(&'static str);
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = String;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::get(self.0)
+                .header(HOST, "users")
+                .body(vec![])
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(String::from_utf8(response.into_body()).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_static_resolver() {
+        let client = DiscoveryClient::new(
+            MyClient,
+            StaticResolver::new().with_service(
+                "users",
+                [
+                    Authority::from_static("users-1.internal:8080"),
+                    Authority::from_static("users-2.internal:8080"),
+                ],
+            ),
+        );
+
+        for expected in [
+            "http://users-1.internal:8080/v1/me?x=1",
+            "http://users-2.internal:8080/v1/me?x=1",
+            "http://users-1.internal:8080/v1/me?x=1",
+        ] {
+            assert_eq!(
+                block_on(client.respond_endpoint(&MyEndpoint("http://users/v1/me?x=1"))).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("https://example.com/"))).unwrap(),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_refresh() {
+        let client = DiscoveryClient::new(MyClient, MyResolver::default());
+        for _ in 0..2 {
+            assert_eq!(
+                block_on(client.respond_endpoint(&MyEndpoint("http://users/"))).unwrap(),
+                "http://10.0.0.1:8080/"
+            );
+        }
+        assert_eq!(client.resolver.calls.load(Ordering::SeqCst), 1);
+
+        // Falls back to the previous addresses.
+        let client = client.with_refresh_interval(Duration::ZERO);
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint("http://users/"))).unwrap(),
+            "http://10.0.0.1:8080/"
+        );
+        assert_eq!(client.resolver.calls.load(Ordering::SeqCst), 2);
+
+        let client = DiscoveryClient::new(MyClient, MyResolver::default());
+        client.resolver.calls.store(1, Ordering::SeqCst);
+        assert!(matches!(
+            block_on(client.respond(MyEndpoint("http://users/").render_request().unwrap())),
+            Err(DiscoveryClientRespondError::ResolveFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let mut resolved = Resolved::new(vec![
+            (Authority::from_static("a:80"), 3),
+            (Authority::from_static("b:80"), 1),
+            (Authority::from_static("c:80"), 0),
+        ]);
+        let picks = (0..8)
+            .map(|_| resolved.pick().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(picks, ["a:80", "a:80", "b:80", "a:80"].repeat(2));
+
+        let mut resolved = Resolved::new(vec![
+            (Authority::from_static("a:80"), 0),
+            (Authority::from_static("b:80"), 0),
+        ]);
+        assert_eq!(resolved.pick().unwrap(), "a:80");
+        assert_eq!(resolved.pick().unwrap(), "b:80");
+
+        assert!(Resolved::new(vec![]).pick().is_none());
+    }
+
+    #[cfg(feature = "dns-srv")]
+    #[test]
+    fn test_lowest_priority_group() {
+        assert_eq!(
+            lowest_priority_group([
+                (20, 5, Some(Authority::from_static("backup:80"))),
+                (10, 0, None),
+                (10, 3, Some(Authority::from_static("a:80"))),
+                (10, 1, Some(Authority::from_static("b:80"))),
+            ]),
+            vec![
+                (Authority::from_static("a:80"), 3),
+                (Authority::from_static("b:80"), 1),
+            ]
+        );
+        assert_eq!(
+            lowest_priority_group([
+                (20, 5, Some(Authority::from_static("backup:80"))),
+                (10, 0, None),
+            ]),
+            vec![(Authority::from_static("backup:80"), 5)]
+        );
+    }
+
+    #[test]
+    fn test_file_resolver() {
+        let path = std::env::temp_dir().join(format!(
+            "http-api-client-discovery-{}.txt",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "# services\n\nusers users-1.internal:8080 users-2.internal\nbroken a:b:c\n",
+        )
+        .unwrap();
+        let resolver = FileResolver::new(&path);
+        // tokio's file I/O needs a tokio runtime.
+        let block_on = |fut| {
+            #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+            let ret = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(fut);
+            #[cfg(not(all(feature = "tokio", not(feature = "async-io"))))]
+            let ret = block_on(fut);
+
+            ret
+        };
+
+        assert_eq!(
+            block_on(resolver.resolve("users")).unwrap(),
+            vec![
+                Authority::from_static("users-1.internal:8080"),
+                Authority::from_static("users-2.internal"),
+            ]
+        );
+        assert!(block_on(resolver.resolve("orders")).unwrap().is_empty());
+        assert_eq!(
+            block_on(resolver.resolve("broken")).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
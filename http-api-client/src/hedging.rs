@@ -0,0 +1,372 @@
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{Body, Idempotent, Request, Response};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+const LATENCY_SAMPLES: usize = 1000;
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// When to send the next copy of a request that hasn't been answered yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HedgeDelay {
+    Fixed(Duration),
+    /// The given percentile, e.g. `0.95`, of the latencies of recent responses,
+    /// `fallback` until enough of them were seen.
+    Percentile {
+        percentile: f64,
+        fallback: Duration,
+    },
+}
+
+/// Sends more copies of a request marked [`Idempotent`] when it takes longer than `delay`,
+/// returning the first response that isn't a server error and dropping the other requests.
+///
+/// Client errors like a 404 count as answers and are returned right away, while a 5xx or a failed
+/// request is only returned once no other copy is left in flight.
+///
+/// At most `max_attempts` copies are sent in total, and at most `max_hedge_ratio` hedges per request
+/// over the life of the client, so that a slow backend isn't flooded.
+/// With the default ratio of `0.1`, the first 9 requests are never hedged.
+/// Other requests are passed through.
+#[derive(Debug, Clone)]
+pub struct HedgingClient<C> {
+    pub client: C,
+    pub delay: HedgeDelay,
+    pub max_attempts: usize,
+    pub max_hedge_ratio: f64,
+    stats: Arc<Mutex<HedgeStats>>,
+}
+
+#[derive(Debug, Default)]
+struct HedgeStats {
+    requests: u64,
+    hedges: u64,
+    latencies: VecDeque<Duration>,
+}
+
+impl HedgeStats {
+    fn try_hedge(&mut self, max_hedge_ratio: f64) -> bool {
+        if (self.hedges + 1) as f64 > self.requests as f64 * max_hedge_ratio {
+            return false;
+        }
+        self.hedges += 1;
+        true
+    }
+
+    fn record_latency(&mut self, latency: Duration) {
+        if self.latencies.len() == LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    fn delay(&self, delay: HedgeDelay) -> Duration {
+        match delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::Percentile { fallback, .. }
+                if self.latencies.len() < MIN_LATENCY_SAMPLES =>
+            {
+                fallback
+            }
+            HedgeDelay::Percentile { percentile, .. } => {
+                let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+                let index = ((latencies.len() - 1) as f64 * percentile.clamp(0.0, 1.0)).round();
+                *latencies.select_nth_unstable(index as usize).1
+            }
+        }
+    }
+}
+
+impl<C> HedgingClient<C> {
+    pub fn new(client: C, delay: HedgeDelay) -> Self {
+        Self {
+            client,
+            delay,
+            max_attempts: 2,
+            max_hedge_ratio: 0.1,
+            stats: Default::default(),
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_max_hedge_ratio(mut self, max_hedge_ratio: f64) -> Self {
+        self.max_hedge_ratio = max_hedge_ratio;
+        self
+    }
+}
+
+type Attempt<'a, T> = (Instant, Pin<Box<dyn Future<Output = T> + Send + 'a>>);
+
+#[async_trait]
+impl<C> Client for HedgingClient<C>
+where
+    C: Client + WithSleeper + Send + Sync,
+{
+    type RespondError = C::RespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        if request.extensions().get::<Idempotent>().is_none() || self.max_attempts <= 1 {
+            return self.client.respond(request).await;
+        }
+
+        let delay = {
+            let mut stats = self.stats.lock().expect("poisoned");
+            stats.requests += 1;
+            stats.delay(self.delay)
+        };
+
+        let mut attempts: Vec<Attempt<'_, _>> =
+            vec![(Instant::now(), self.client.respond(request.clone()))];
+        let mut sent = 1;
+        let mut timer = Some(self.client.sleeper().sleep(delay));
+        let mut last = None;
+
+        let (started_at, ret) = poll_fn(|cx| loop {
+            let mut i = 0;
+            while i < attempts.len() {
+                let Poll::Ready(ret) = attempts[i].1.as_mut().poll(cx) else {
+                    i += 1;
+                    continue;
+                };
+                let (started_at, _) = attempts.swap_remove(i);
+                match ret {
+                    Ok(res) if !res.status().is_server_error() => {
+                        return Poll::Ready((started_at, Ok(res)))
+                    }
+                    ret => last = Some((started_at, ret)),
+                }
+            }
+
+            let hedge = match timer.as_mut().map(|timer| timer.as_mut().poll(cx)) {
+                Some(Poll::Ready(())) => {
+                    timer = None;
+                    !attempts.is_empty()
+                        && self
+                            .stats
+                            .lock()
+                            .expect("poisoned")
+                            .try_hedge(self.max_hedge_ratio)
+                }
+                _ => false,
+            };
+            if hedge {
+                attempts.push((Instant::now(), self.client.respond(request.clone())));
+                sent += 1;
+                if sent < self.max_attempts {
+                    timer = Some(self.client.sleeper().sleep(delay));
+                }
+                continue;
+            }
+
+            if attempts.is_empty() {
+                if let Some(last) = last.take() {
+                    return Poll::Ready(last);
+                }
+            }
+            return Poll::Pending;
+        })
+        .await;
+
+        if ret.is_ok() {
+            self.stats
+                .lock()
+                .expect("poisoned")
+                .record_latency(started_at.elapsed());
+        }
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for HedgingClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::{http::Method, Endpoint};
+
+    use crate::sleep::VirtualClockSleeper;
+
+    struct DropGuard(Arc<AtomicUsize>);
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // The first `pending` calls never complete, the others answer with the call index,
+    // on their second poll when `slow`.
+    #[derive(Debug, Default)]
+    struct MyClient {
+        pending: usize,
+        slow: bool,
+        calls: AtomicUsize,
+        dropped: Arc<AtomicUsize>,
+        sleeper: VirtualClockSleeper,
+    }
+    #[async_trait]
+    impl Client for MyClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            _request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.pending {
+                let _guard = DropGuard(self.dropped.clone());
+                core::future::pending::<()>().await;
+            }
+            if self.slow {
+                let mut polled = false;
+                poll_fn(|cx| {
+                    if polled {
+                        return Poll::Ready(());
+                    }
+                    polled = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                })
+                .await;
+            }
+            Ok(Response::new(call.to_string().into_bytes()))
+        }
+    }
+    impl WithSleeper for MyClient {
+        fn sleeper(&self) -> &dyn Sleeper {
+            &self.sleeper
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint(Method);
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = String;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::builder()
+                .method(self.0.to_owned())
+                .uri("http://example.com/")
+                .body(vec![])
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(String::from_utf8(response.into_body()).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_hedge() {
+        let client = HedgingClient::new(
+            MyClient {
+                pending: 2,
+                ..Default::default()
+            },
+            HedgeDelay::Fixed(Duration::from_millis(50)),
+        )
+        .with_max_attempts(3)
+        .with_max_hedge_ratio(2.0);
+
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint(Method::GET))).unwrap(),
+            "2"
+        );
+        assert_eq!(client.client.dropped.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            client.client.sleeper.sleeps(),
+            vec![Duration::from_millis(50); 2]
+        );
+
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint(Method::POST))).unwrap(),
+            "3"
+        );
+        assert_eq!(client.client.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_max_hedge_ratio() {
+        let client = HedgingClient::new(
+            MyClient {
+                slow: true,
+                ..Default::default()
+            },
+            HedgeDelay::Fixed(Duration::from_millis(50)),
+        );
+
+        for i in 0..9 {
+            assert_eq!(
+                block_on(client.respond_endpoint(&MyEndpoint(Method::GET))).unwrap(),
+                i.to_string()
+            );
+        }
+        assert_eq!(client.client.sleeper.sleeps().len(), 9);
+        assert_eq!(client.stats.lock().unwrap().hedges, 0);
+
+        assert_eq!(
+            block_on(client.respond_endpoint(&MyEndpoint(Method::GET))).unwrap(),
+            "9"
+        );
+        assert_eq!(client.stats.lock().unwrap().hedges, 1);
+    }
+
+    #[test]
+    fn test_hedge_stats() {
+        let mut stats = HedgeStats {
+            requests: 10,
+            ..Default::default()
+        };
+        assert!(stats.try_hedge(0.2));
+        assert!(stats.try_hedge(0.2));
+        assert!(!stats.try_hedge(0.2));
+        assert_eq!(stats.hedges, 2);
+
+        let delay = HedgeDelay::Percentile {
+            percentile: 0.9,
+            fallback: Duration::from_secs(1),
+        };
+        assert_eq!(stats.delay(delay), Duration::from_secs(1));
+        for ms in (1..=100).rev() {
+            stats.record_latency(Duration::from_millis(ms));
+        }
+        assert_eq!(stats.delay(delay), Duration::from_millis(90));
+    }
+}
@@ -0,0 +1,275 @@
+use core::{fmt, future::Future, pin::Pin, task::Poll};
+use std::{convert::Infallible, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_api_client_endpoint::{Body, MaxResponseBodySize, Request, Response};
+use http_body::Body as HttpBody;
+use http_body_util::{BodyExt as _, Full, LengthLimitError, Limited};
+use tower_service::Service;
+
+use crate::{Client, ClientMetrics};
+
+pub type InProcessBody = Full<Bytes>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Dispatches requests to a `tower::Service` (e.g. an axum `Router`) without any socket.
+#[derive(Debug, Clone)]
+pub struct InProcessClient<S> {
+    pub service: S,
+    pub metrics: Option<Arc<dyn ClientMetrics>>,
+    pub max_response_body_size: Option<usize>,
+}
+
+impl<S> InProcessClient<S> {
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            metrics: None,
+            max_response_body_size: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn ClientMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+}
+
+impl<F> InProcessClient<FnService<F>> {
+    pub fn from_fn<Fut>(f: F) -> Self
+    where
+        F: Fn(Request<Body>) -> Fut,
+        Fut: Future<Output = Response<Body>>,
+    {
+        Self::new(FnService(f))
+    }
+}
+
+impl<S, ResB> InProcessClient<S>
+where
+    S: Service<Request<InProcessBody>, Response = Response<ResB>> + Clone + Send + Sync,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    ResB: HttpBody + Send,
+    ResB::Data: Send,
+    ResB::Error: Into<BoxError>,
+{
+    async fn execute(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, InProcessClientRespondError> {
+        let max_body_size = request
+            .extensions()
+            .get::<MaxResponseBodySize>()
+            .map(|MaxResponseBodySize(x)| *x)
+            .or(self.max_response_body_size);
+
+        let mut service = self.service.clone();
+
+        core::future::poll_fn(|cx| service.poll_ready(cx))
+            .await
+            .map_err(|err| InProcessClientRespondError::ServiceFailed(err.into()))?;
+
+        let res = service
+            .call(request.map(|body| Full::new(Bytes::from(body))))
+            .await
+            .map_err(|err| InProcessClientRespondError::ServiceFailed(err.into()))?;
+
+        let (head, body) = res.into_parts();
+        let body = Limited::new(body, max_body_size.unwrap_or(usize::MAX))
+            .collect()
+            .await
+            .map_err(|err| match max_body_size {
+                Some(limit) if err.is::<LengthLimitError>() => {
+                    InProcessClientRespondError::BodyTooLarge { limit }
+                }
+                _ => InProcessClientRespondError::ReadBodyFailed(err),
+            })?
+            .to_bytes();
+
+        Ok(Response::from_parts(head, Vec::from(body)))
+    }
+}
+
+#[async_trait]
+impl<S, ResB> Client for InProcessClient<S>
+where
+    S: Service<Request<InProcessBody>, Response = Response<ResB>> + Clone + Send + Sync,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    ResB: HttpBody + Send,
+    ResB::Data: Send,
+    ResB::Error: Into<BoxError>,
+{
+    type RespondError = InProcessClientRespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        #[cfg(feature = "tracing")]
+        let ret =
+            crate::instrumentation::instrument_respond(request, |request| self.execute(request))
+                .await;
+        #[cfg(not(feature = "tracing"))]
+        let ret = self.execute(request).await;
+
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+}
+
+//
+/// Adapts a `Fn(Request<Body>) -> impl Future<Output = Response<Body>>` to a `tower::Service`.
+#[derive(Clone)]
+pub struct FnService<F>(pub F);
+
+impl<F> fmt::Debug for FnService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FnService").finish()
+    }
+}
+
+impl<F, Fut> Service<Request<InProcessBody>> for FnService<F>
+where
+    F: Fn(Request<Body>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Response<Body>> + Send,
+{
+    type Response = Response<InProcessBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut core::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<InProcessBody>) -> Self::Future {
+        let f = self.0.clone();
+        Box::pin(async move {
+            let (head, body) = request.into_parts();
+            let body = body.collect().await?.to_bytes();
+            let res = f(Request::from_parts(head, Vec::from(body))).await;
+            Ok(res.map(|body| Full::new(Bytes::from(body))))
+        })
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum InProcessClientRespondError {
+    ServiceFailed(BoxError),
+    ReadBodyFailed(BoxError),
+    BodyTooLarge { limit: usize },
+}
+impl fmt::Display for InProcessClientRespondError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for InProcessClientRespondError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ServiceFailed(err) => Some(err.as_ref()),
+            Self::ReadBodyFailed(err) => Some(err.as_ref()),
+            Self::BodyTooLarge { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::{http::StatusCode, Endpoint};
+
+    #[derive(Clone)]
+    struct MyEchoEndpoint;
+    impl Endpoint for MyEchoEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = Response<Body>;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::builder()
+                .uri("/echo")
+                .header("X-Foo", "foo")
+                .body(b"ping".to_vec())
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(response)
+        }
+    }
+
+    async fn echo(request: Request<Body>) -> Response<Body> {
+        let (head, body) = request.into_parts();
+        let mut res = Response::new([head.uri.path().as_bytes(), b" ", &body].concat());
+        *res.status_mut() = StatusCode::CREATED;
+        if let Some(x_foo) = head.headers.get("X-Foo") {
+            res.headers_mut().insert("X-Bar", x_foo.to_owned());
+        }
+        res
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let client = InProcessClient::from_fn(echo);
+        let res = block_on(client.respond_endpoint(&MyEchoEndpoint)).unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers().get("X-Bar").unwrap(), "foo");
+        assert_eq!(res.body(), b"/echo ping");
+
+        let client = InProcessClient::from_fn(echo).with_max_response_body_size(4);
+        assert!(matches!(
+            block_on(client.respond_endpoint(&MyEchoEndpoint)),
+            Err(crate::ClientRespondEndpointError::RespondFailed(
+                InProcessClientRespondError::BodyTooLarge { limit: 4 }
+            ))
+        ));
+    }
+
+    #[derive(Clone)]
+    struct MyFailingService;
+    impl Service<Request<InProcessBody>> for MyFailingService {
+        type Response = Response<InProcessBody>;
+        type Error = IoError;
+        type Future = core::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut core::task::Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<InProcessBody>) -> Self::Future {
+            core::future::ready(Err(IoError::other("boom")))
+        }
+    }
+
+    #[test]
+    fn test_service_failed() {
+        let client = InProcessClient::new(MyFailingService);
+        match block_on(client.respond_endpoint(&MyEchoEndpoint)) {
+            Err(crate::ClientRespondEndpointError::RespondFailed(
+                InProcessClientRespondError::ServiceFailed(err),
+            )) => assert_eq!(err.to_string(), "boom"),
+            ret => panic!("{:?}", ret.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Spans following the OpenTelemetry HTTP client semantic conventions.
+
+use core::{future::Future, time::Duration};
+
+use http_api_client_endpoint::{Body, EndpointMetadata, Request, Response};
+use tracing::{field::Empty, Instrument as _, Span};
+
+pub const FIELD_ENDPOINT: &str = "http_api_client.endpoint";
+pub const FIELD_OPERATION_ID: &str = "http_api_client.operation_id";
+pub const FIELD_TAGS: &str = "http_api_client.tags";
+
+pub fn endpoint_span(endpoint: &str, metadata: &EndpointMetadata) -> Span {
+    tracing::info_span!(
+        "respond_endpoint",
+        otel.kind = "client",
+        { FIELD_ENDPOINT } = endpoint,
+        { FIELD_OPERATION_ID } = metadata.operation_id.as_deref(),
+        { FIELD_TAGS } = ?metadata.tags,
+        "http.request.method" = Empty,
+        "url.full" = Empty,
+        "http.response.status_code" = Empty,
+        "error.type" = Empty,
+        "otel.status_code" = Empty,
+    )
+}
+
+pub fn endpoint_until_done_span(endpoint: &str, metadata: &EndpointMetadata) -> Span {
+    tracing::info_span!(
+        "respond_endpoint_until_done",
+        otel.kind = "client",
+        { FIELD_ENDPOINT } = endpoint,
+        { FIELD_OPERATION_ID } = metadata.operation_id.as_deref(),
+        { FIELD_TAGS } = ?metadata.tags,
+        "error.type" = Empty,
+        "otel.status_code" = Empty,
+    )
+}
+
+pub fn endpoint_attempt_span(attempt: usize) -> Span {
+    tracing::info_span!(
+        "attempt",
+        "http.request.resend_count" = attempt,
+        "http.request.method" = Empty,
+        "url.full" = Empty,
+        "http.response.status_code" = Empty,
+        "error.type" = Empty,
+        "otel.status_code" = Empty,
+    )
+}
+
+pub fn respond_span(request: &Request<Body>) -> Span {
+    let uri = request.uri();
+
+    tracing::info_span!(
+        "respond",
+        otel.name = %request.method(),
+        otel.kind = "client",
+        "http.request.method" = %request.method(),
+        "url.full" = %uri,
+        "server.address" = uri.host(),
+        "server.port" = uri.port_u16(),
+        "network.protocol.version" = ?request.version(),
+        "http.response.status_code" = Empty,
+        "error.type" = Empty,
+        "otel.status_code" = Empty,
+    )
+}
+
+pub fn record_request(span: &Span, request: &Request<Body>) {
+    span.record(
+        "http.request.method",
+        tracing::field::display(request.method()),
+    );
+    span.record("url.full", tracing::field::display(request.uri()));
+}
+
+pub fn record_response(span: &Span, response: &Response<Body>) {
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_client_error() || status.is_server_error() {
+        span.record("error.type", status.as_str());
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+pub fn record_error(span: &Span, kind: &str) {
+    span.record("error.type", kind);
+    span.record("otel.status_code", "ERROR");
+}
+
+pub fn record_retry(retry_count: usize, sleep: Duration, reason: Option<&str>) {
+    tracing::debug!(
+        "http.request.resend_count" = retry_count,
+        "http_api_client.retry.reason" = reason,
+        "http_api_client.retry.sleep_ms" = sleep.as_millis() as u64,
+        "retry",
+    );
+}
+
+/// Runs a [`Client::respond`](crate::Client::respond) implementation inside a [`respond_span`].
+pub async fn instrument_respond<F, Fut, E>(
+    #[allow(unused_mut)] mut request: Request<Body>,
+    f: F,
+) -> Result<Response<Body>, E>
+where
+    F: FnOnce(Request<Body>) -> Fut,
+    Fut: Future<Output = Result<Response<Body>, E>>,
+    E: std::error::Error,
+{
+    let span = respond_span(&request);
+
+    #[cfg(feature = "opentelemetry")]
+    inject_trace_context(&span, &mut request);
+
+    let ret = f(request).instrument(span.clone()).await;
+    match &ret {
+        Ok(response) => record_response(&span, response),
+        Err(err) => {
+            record_error(&span, "RespondFailed");
+            tracing::debug!(parent: &span, error = %err, "respond failed");
+        }
+    }
+    ret
+}
+
+/// Blocking counterpart of [`instrument_respond`].
+pub fn instrument_respond_blocking<F, E>(
+    #[allow(unused_mut)] mut request: Request<Body>,
+    f: F,
+) -> Result<Response<Body>, E>
+where
+    F: FnOnce(Request<Body>) -> Result<Response<Body>, E>,
+    E: std::error::Error,
+{
+    let span = respond_span(&request);
+
+    #[cfg(feature = "opentelemetry")]
+    inject_trace_context(&span, &mut request);
+
+    let ret = span.in_scope(|| f(request));
+    match &ret {
+        Ok(response) => record_response(&span, response),
+        Err(err) => {
+            record_error(&span, "RespondFailed");
+            tracing::debug!(parent: &span, error = %err, "respond failed");
+        }
+    }
+    ret
+}
+
+/// Sets the W3C `traceparent` and `tracestate` headers from the OpenTelemetry context of `span`,
+/// unless `traceparent` is already present.
+#[cfg(feature = "opentelemetry")]
+pub fn inject_trace_context(span: &Span, request: &mut Request<Body>) {
+    use http_api_client_endpoint::http::HeaderValue;
+    use opentelemetry::trace::TraceContextExt as _;
+    use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+    if request.headers().contains_key("traceparent") {
+        return;
+    }
+
+    let context = span.context();
+    let otel_span = context.span();
+    let span_context = otel_span.span_context();
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    );
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        request.headers_mut().insert("traceparent", value);
+    }
+
+    let tracestate = span_context.trace_state().header();
+    if !tracestate.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&tracestate) {
+            request.headers_mut().insert("tracestate", value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "opentelemetry"))]
+mod tests {
+    use super::*;
+
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    #[test]
+    fn test_instrument_respond_injects_traceparent() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = Request::builder()
+                .uri("http://example.com/")
+                .body(vec![])
+                .unwrap();
+
+            let traceparent = block_on(instrument_respond(request, |request| async move {
+                Ok::<_, IoError>(Response::new(
+                    request
+                        .headers()
+                        .get("traceparent")
+                        .map(|x| x.as_bytes().to_owned())
+                        .unwrap_or_default(),
+                ))
+            }))
+            .unwrap()
+            .into_body();
+
+            let traceparent = String::from_utf8(traceparent).unwrap();
+            let parts = traceparent.split('-').collect::<Vec<_>>();
+            assert_eq!(parts.len(), 4);
+            assert_eq!(parts[0], "00");
+            assert_eq!(parts[1].len(), 32);
+            assert_eq!(parts[2].len(), 16);
+        });
+    }
+}
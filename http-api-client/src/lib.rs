@@ -1,15 +1,90 @@
-use core::time::Duration;
+use core::{future::Future, time::Duration};
+use std::{borrow::Cow, time::Instant};
 
 pub use async_trait::async_trait;
-pub use http_api_client_endpoint::{http, Body, Request, Response};
+pub use http_api_client_endpoint::{
+    http, Body, ContentCoding, EffectiveUri, Idempotent, MaxResponseBodySize, RedirectPolicy,
+    RemoteAddr, Request, RequestCompression, RequestProxy, RequestTimeout, Response,
+    ResponseTimings, TlsIdentity, VersionPreference,
+};
 use http_api_client_endpoint::{Endpoint, RetryableEndpoint, RetryableEndpointRetry};
 
+pub mod blocking;
+mod cancellation;
+pub mod coalescing;
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub mod compression;
+pub mod config;
+#[cfg(feature = "cookies")]
+pub mod cookie;
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub mod decompression;
+pub mod discovery;
+pub mod hedging;
+#[cfg(feature = "in-process")]
+pub mod in_process;
+#[cfg(feature = "tracing")]
+pub mod instrumentation;
+pub mod load_balancing;
+pub mod metrics;
+pub mod redirect;
+mod respond;
+pub mod sleep;
+#[cfg(feature = "rustls")]
+pub mod tls;
+
+pub use blocking::BlockingClient;
+pub use coalescing::CoalescingClient;
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub use compression::CompressionClient;
+pub use config::{ClientConfig, ProxyConfig};
+#[cfg(feature = "cookies")]
+pub use cookie::{CookieClient, CookieJar};
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub use decompression::DecompressionClient;
+pub use discovery::{DiscoveryClient, Resolver};
+pub use hedging::HedgingClient;
+#[cfg(feature = "in-process")]
+pub use in_process::InProcessClient;
+pub use load_balancing::LoadBalancingClient;
+pub use metrics::ClientMetrics;
+pub use redirect::RedirectClient;
+pub use sleep::{Sleeper, SleeperClient, WithSleeper};
+
+use respond::{next_retry, prepare_request, set_idempotency_key, EndpointOptions};
+
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
 #[async_trait]
 pub trait Client {
     type RespondError: std::error::Error + Send + Sync + 'static;
 
     async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError>;
 
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        None
+    }
+
     async fn respond_endpoint<EP>(
         &self,
         endpoint: &EP,
@@ -28,6 +103,27 @@ pub trait Client {
             .await
     }
 
+    async fn respond_endpoint_with_cancellation<EP, C>(
+        &self,
+        endpoint: &EP,
+        cancellation: C,
+    ) -> Result<
+        EP::ParseResponseOutput,
+        ClientRespondEndpointError<
+            Self::RespondError,
+            EP::RenderRequestError,
+            EP::ParseResponseError,
+        >,
+    >
+    where
+        EP: Endpoint + Send + Sync,
+        C: Future<Output = ()> + Send,
+    {
+        cancellation::or_cancelled(self.respond_endpoint(endpoint), cancellation)
+            .await
+            .unwrap_or(Err(ClientRespondEndpointError::Cancelled))
+    }
+
     async fn respond_endpoint_with_callback<EP, PreRCB, PostRCB>(
         &self,
         endpoint: &EP,
@@ -88,22 +184,59 @@ pub trait Client {
         PreRCB: FnMut(Request<Body>) -> Request<Body> + Send,
         PostRCB: FnMut(&Response<Body>) + Send,
     {
-        let request = endpoint
-            .render_request()
-            .map_err(ClientRespondEndpointError::EndpointRenderRequestFailed)?;
+        let endpoint_name = endpoint.name();
+        let options = EndpointOptions::of(endpoint);
 
-        let request = pre_request_callback(request);
+        #[cfg(feature = "tracing")]
+        let span = instrumentation::endpoint_span(&endpoint_name, &options.metadata);
 
-        let response = self
-            .respond(request)
-            .await
-            .map_err(ClientRespondEndpointError::RespondFailed)?;
+        let fut = async {
+            let mut request = endpoint
+                .render_request()
+                .map_err(ClientRespondEndpointError::EndpointRenderRequestFailed)?;
+
+            prepare_request(&options, &mut request);
+
+            let request = pre_request_callback(request);
+
+            #[cfg(feature = "tracing")]
+            instrumentation::record_request(&span, &request);
+
+            let method = request.method().to_owned();
+            let started_at = Instant::now();
+
+            let ret = self.respond(request).await;
+
+            respond::record_request(self.metrics(), &endpoint_name, &method, started_at, &ret);
+
+            let response = ret.map_err(ClientRespondEndpointError::RespondFailed)?;
+
+            #[cfg(feature = "tracing")]
+            instrumentation::record_response(&span, &response);
+
+            post_request_callback(&response);
 
-        post_request_callback(&response);
+            endpoint
+                .parse_response(response)
+                .map_err(ClientRespondEndpointError::EndpointParseResponseFailed)
+        };
 
-        endpoint
-            .parse_response(response)
-            .map_err(ClientRespondEndpointError::EndpointParseResponseFailed)
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, span.clone());
+
+        let ret = fut.await;
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &ret {
+            instrumentation::record_error(&span, err.kind());
+            tracing::debug!(
+                parent: &span,
+                error = %EndpointError { endpoint: endpoint_name, error: err },
+                "respond endpoint failed"
+            );
+        }
+
+        ret
     }
 }
 
@@ -129,6 +262,27 @@ pub trait RetryableClient: Client {
             .await
     }
 
+    async fn respond_endpoint_until_done_with_cancellation<EP, C>(
+        &self,
+        endpoint: &EP,
+        cancellation: C,
+    ) -> Result<
+        EP::ParseResponseOutput,
+        RetryableClientRespondEndpointUntilDoneError<
+            Self::RespondError,
+            EP::RenderRequestError,
+            EP::ParseResponseError,
+        >,
+    >
+    where
+        EP: RetryableEndpoint + Send + Sync,
+        C: Future<Output = ()> + Send,
+    {
+        cancellation::or_cancelled(self.respond_endpoint_until_done(endpoint), cancellation)
+            .await
+            .unwrap_or(Err(RetryableClientRespondEndpointUntilDoneError::Cancelled))
+    }
+
     async fn respond_endpoint_until_done_with_callback<EP, PreRCB, PostRCB>(
         &self,
         endpoint: &EP,
@@ -148,42 +302,114 @@ pub trait RetryableClient: Client {
             + Send,
         PostRCB: FnMut(&Response<Body>, Option<&RetryableEndpointRetry<EP::RetryReason>>) + Send,
     {
-        let mut retry = None;
-
-        loop {
-            let request = endpoint.render_request(retry.as_ref()).map_err(
-                RetryableClientRespondEndpointUntilDoneError::EndpointRenderRequestFailed,
-            )?;
-
-            let request = pre_request_callback(request, retry.as_ref());
-
-            let response = self
-                .respond(request)
-                .await
-                .map_err(RetryableClientRespondEndpointUntilDoneError::RespondFailed)?;
-
-            post_request_callback(&response, retry.as_ref());
-
-            match endpoint.parse_response(response, retry.as_ref()).map_err(
-                RetryableClientRespondEndpointUntilDoneError::EndpointParseResponseFailed,
-            )? {
-                Ok(output) => return Ok(output),
-                Err(reason) => {
-                    let x = retry.get_or_insert(RetryableEndpointRetry::new(0, reason.clone()));
-                    x.count += 1;
-                    x.reason = reason;
+        let endpoint_name = endpoint.name();
+        let options = EndpointOptions::of_retryable(endpoint);
+
+        #[cfg(feature = "tracing")]
+        let span = instrumentation::endpoint_until_done_span(&endpoint_name, &options.metadata);
+
+        let fut = async {
+            let mut retry = None;
+            let mut idempotency_key = None;
+
+            loop {
+                let attempt = async {
+                    let mut request = endpoint.render_request(retry.as_ref()).map_err(
+                        RetryableClientRespondEndpointUntilDoneError::EndpointRenderRequestFailed,
+                    )?;
+
+                    let idempotent = prepare_request(&options, &mut request);
+                    if !idempotent {
+                        set_idempotency_key(&mut request, &mut idempotency_key);
+                    }
+
+                    let request = pre_request_callback(request, retry.as_ref());
+
+                    let retryable_on_respond_failed =
+                        idempotent || request.headers().contains_key(IDEMPOTENCY_KEY);
+
+                    #[cfg(feature = "tracing")]
+                    instrumentation::record_request(&tracing::Span::current(), &request);
+
+                    let method = request.method().to_owned();
+                    let started_at = Instant::now();
+
+                    let ret = self.respond(request).await;
+
+                    respond::record_request(
+                        self.metrics(),
+                        &endpoint_name,
+                        &method,
+                        started_at,
+                        &ret,
+                    );
+
+                    let response = match ret {
+                        Ok(response) => response,
+                        Err(err) => {
+                            if retryable_on_respond_failed {
+                                if let Some(reason) =
+                                    endpoint.retry_on_respond_failed(&err, retry.as_ref())
+                                {
+                                    return Ok(Err(reason));
+                                }
+                            }
+                            return Err(
+                                RetryableClientRespondEndpointUntilDoneError::RespondFailed(err),
+                            );
+                        }
+                    };
+
+                    #[cfg(feature = "tracing")]
+                    instrumentation::record_response(&tracing::Span::current(), &response);
+
+                    post_request_callback(&response, retry.as_ref());
+
+                    endpoint.parse_response(response, retry.as_ref()).map_err(
+                        RetryableClientRespondEndpointUntilDoneError::EndpointParseResponseFailed,
+                    )
+                };
+                #[cfg(feature = "tracing")]
+                let attempt = tracing::Instrument::instrument(
+                    attempt,
+                    instrumentation::endpoint_attempt_span(
+                        retry
+                            .as_ref()
+                            .map(|x: &RetryableEndpointRetry<_>| x.count)
+                            .unwrap_or(0),
+                    ),
+                );
+
+                let reason = match attempt.await? {
+                    Ok(output) => return Ok(output),
+                    Err(reason) => reason,
+                };
+
+                match next_retry(endpoint, &endpoint_name, self.metrics(), &mut retry, reason) {
+                    Some(dur) => self.sleep(dur).await,
+                    None => {
+                        return Err(RetryableClientRespondEndpointUntilDoneError::ReachedMaxRetries)
+                    }
                 }
             }
+        };
 
-            //
-            if let Some(retry) = &retry {
-                if retry.count >= endpoint.max_retry_count() {
-                    return Err(RetryableClientRespondEndpointUntilDoneError::ReachedMaxRetries);
-                }
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, span.clone());
 
-                self.sleep(endpoint.next_retry_in(retry)).await;
-            }
+        let ret = fut.await;
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &ret {
+            instrumentation::record_error(&span, err.kind());
+            tracing::debug!(
+                parent: &span,
+                error = %EndpointError { endpoint: endpoint_name, error: err },
+                "respond endpoint failed"
+            );
         }
+
+        ret
     }
 }
 
@@ -198,6 +424,30 @@ where
     RespondFailed(RE),
     EndpointRenderRequestFailed(EPRRE),
     EndpointParseResponseFailed(EPPRE),
+    Cancelled,
+}
+impl<RE, EPRRE, EPPRE> ClientRespondEndpointError<RE, EPRRE, EPPRE>
+where
+    RE: std::error::Error + Send + Sync + 'static,
+    EPRRE: std::error::Error + Send + Sync + 'static,
+    EPPRE: std::error::Error + Send + Sync + 'static,
+{
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RespondFailed(_) => "RespondFailed",
+            Self::EndpointRenderRequestFailed(_) => "EndpointRenderRequestFailed",
+            Self::EndpointParseResponseFailed(_) => "EndpointParseResponseFailed",
+            Self::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Attaches the [`Endpoint::name`], so it is shown as `{endpoint}: {self}`.
+    pub fn with_endpoint(self, endpoint: impl Into<Cow<'static, str>>) -> EndpointError<Self> {
+        EndpointError {
+            endpoint: endpoint.into(),
+            error: self,
+        }
+    }
 }
 impl<RE, EPRRE, EPPRE> core::fmt::Display for ClientRespondEndpointError<RE, EPRRE, EPPRE>
 where
@@ -206,7 +456,12 @@ where
     EPPRE: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::RespondFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::EndpointRenderRequestFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::EndpointParseResponseFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::Cancelled => write!(f, "{}", self.kind()),
+        }
     }
 }
 impl<RE, EPRRE, EPPRE> std::error::Error for ClientRespondEndpointError<RE, EPRRE, EPPRE>
@@ -215,6 +470,14 @@ where
     EPRRE: std::error::Error + Send + Sync + 'static,
     EPPRE: std::error::Error + Send + Sync + 'static,
 {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::EndpointRenderRequestFailed(err) => Some(err),
+            Self::EndpointParseResponseFailed(err) => Some(err),
+            Self::Cancelled => None,
+        }
+    }
 }
 
 //
@@ -229,6 +492,31 @@ where
     EndpointRenderRequestFailed(EPRRE),
     EndpointParseResponseFailed(EPPRE),
     ReachedMaxRetries,
+    Cancelled,
+}
+impl<RE, EPRRE, EPPRE> RetryableClientRespondEndpointUntilDoneError<RE, EPRRE, EPPRE>
+where
+    RE: std::error::Error + Send + Sync + 'static,
+    EPRRE: std::error::Error + Send + Sync + 'static,
+    EPPRE: std::error::Error + Send + Sync + 'static,
+{
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RespondFailed(_) => "RespondFailed",
+            Self::EndpointRenderRequestFailed(_) => "EndpointRenderRequestFailed",
+            Self::EndpointParseResponseFailed(_) => "EndpointParseResponseFailed",
+            Self::ReachedMaxRetries => "ReachedMaxRetries",
+            Self::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Attaches the [`Endpoint::name`], so it is shown as `{endpoint}: {self}`.
+    pub fn with_endpoint(self, endpoint: impl Into<Cow<'static, str>>) -> EndpointError<Self> {
+        EndpointError {
+            endpoint: endpoint.into(),
+            error: self,
+        }
+    }
 }
 impl<RE, EPRRE, EPPRE> core::fmt::Display
     for RetryableClientRespondEndpointUntilDoneError<RE, EPRRE, EPPRE>
@@ -238,7 +526,12 @@ where
     EPPRE: std::error::Error + Send + Sync + 'static,
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::RespondFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::EndpointRenderRequestFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::EndpointParseResponseFailed(err) => write!(f, "{}: {}", self.kind(), err),
+            Self::ReachedMaxRetries | Self::Cancelled => write!(f, "{}", self.kind()),
+        }
     }
 }
 impl<RE, EPRRE, EPPRE> std::error::Error
@@ -248,13 +541,52 @@ where
     EPRRE: std::error::Error + Send + Sync + 'static,
     EPPRE: std::error::Error + Send + Sync + 'static,
 {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::EndpointRenderRequestFailed(err) => Some(err),
+            Self::EndpointParseResponseFailed(err) => Some(err),
+            Self::ReachedMaxRetries | Self::Cancelled => None,
+        }
+    }
+}
+
+//
+/// An error together with the name of the endpoint it came from.
+#[derive(Debug)]
+pub struct EndpointError<E> {
+    pub endpoint: Cow<'static, str>,
+    pub error: E,
+}
+impl<E> core::fmt::Display for EndpointError<E>
+where
+    E: core::fmt::Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.endpoint, self.error)
+    }
+}
+impl<E> std::error::Error for EndpointError<E>
+where
+    E: std::error::Error,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use std::{collections::HashMap, io::Error as IoError, panic};
+    use std::{
+        collections::HashMap,
+        io::Error as IoError,
+        panic,
+        sync::{Arc, Mutex},
+    };
+
+    use http::{HeaderValue, Method, StatusCode};
 
     use futures_executor::block_on;
 
@@ -331,4 +663,332 @@ mod tests {
             err => panic!("{:?}", err),
         }
     }
+
+    #[test]
+    fn test_dyn_endpoint_debug() {
+        let endpoint: Box<
+            dyn Endpoint<
+                    RenderRequestError = IoError,
+                    ParseResponseOutput = (),
+                    ParseResponseError = IoError,
+                > + Send
+                + Sync,
+        > = Box::new(MyEndpoint);
+        assert_eq!(
+            format!("{:?}", endpoint),
+            r#"Endpoint { name: "http_api_client::tests::MyEndpoint", metadata: EndpointMetadata { operation_id: None, tags: [], idempotent: None } }"#
+        );
+    }
+
+    #[derive(Debug, Default)]
+    struct MyMetrics {
+        records: Mutex<Vec<String>>,
+    }
+    impl ClientMetrics for MyMetrics {
+        fn record_request(
+            &self,
+            endpoint: &str,
+            method: &Method,
+            status: Option<StatusCode>,
+            _latency: Duration,
+        ) {
+            self.records.lock().unwrap().push(format!(
+                "request {} {} {}",
+                endpoint,
+                method,
+                metrics::status_class(status)
+            ));
+        }
+
+        fn record_retry(&self, endpoint: &str, retry_count: usize) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("retry {} {}", endpoint, retry_count));
+        }
+
+        fn record_give_up(&self, endpoint: &str) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("give_up {}", endpoint));
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyRetryableEndpoint;
+    impl RetryableEndpoint for MyRetryableEndpoint {
+        type RetryReason = StatusCode;
+
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = ();
+        type ParseResponseError = IoError;
+
+        fn render_request(
+            &self,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::new(vec![]))
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Result<Self::ParseResponseOutput, Self::RetryReason>, Self::ParseResponseError>
+        {
+            if response.status().is_success() {
+                Ok(Ok(()))
+            } else {
+                Ok(Err(response.status()))
+            }
+        }
+
+        fn max_retry_count(&self) -> usize {
+            2
+        }
+
+        fn name(&self) -> std::borrow::Cow<'static, str> {
+            "my_retryable_endpoint".into()
+        }
+    }
+
+    #[derive(Debug)]
+    struct MyMetricsClient {
+        metrics: Arc<MyMetrics>,
+        statuses: Mutex<Vec<StatusCode>>,
+    }
+    #[async_trait]
+    impl Client for MyMetricsClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            _request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let mut res = Response::new(vec![]);
+            *res.status_mut() = self.statuses.lock().unwrap().remove(0);
+            Ok(res)
+        }
+
+        fn metrics(&self) -> Option<&dyn ClientMetrics> {
+            Some(self.metrics.as_ref())
+        }
+    }
+    #[async_trait]
+    impl RetryableClient for MyMetricsClient {
+        async fn sleep(&self, _dur: Duration) {}
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Arc::new(MyMetrics::default());
+        let client = MyMetricsClient {
+            metrics: metrics.clone(),
+            statuses: Mutex::new(vec![StatusCode::SERVICE_UNAVAILABLE, StatusCode::OK]),
+        };
+        block_on(client.respond_endpoint_until_done(&MyRetryableEndpoint)).unwrap();
+        assert_eq!(
+            *metrics.records.lock().unwrap(),
+            vec![
+                "request my_retryable_endpoint GET 5xx",
+                "retry my_retryable_endpoint 1",
+                "request my_retryable_endpoint GET 2xx",
+            ]
+        );
+
+        let metrics = Arc::new(MyMetrics::default());
+        let client = MyMetricsClient {
+            metrics: metrics.clone(),
+            statuses: Mutex::new(vec![StatusCode::SERVICE_UNAVAILABLE; 2]),
+        };
+        assert!(matches!(
+            block_on(client.respond_endpoint_until_done(&MyRetryableEndpoint)),
+            Err(RetryableClientRespondEndpointUntilDoneError::ReachedMaxRetries)
+        ));
+        assert_eq!(
+            *metrics.records.lock().unwrap(),
+            vec![
+                "request my_retryable_endpoint GET 5xx",
+                "retry my_retryable_endpoint 1",
+                "request my_retryable_endpoint GET 5xx",
+                "give_up my_retryable_endpoint",
+            ]
+        );
+    }
+
+    #[derive(Clone)]
+    struct MyCreateEndpoint;
+    impl RetryableEndpoint for MyCreateEndpoint {
+        type RetryReason = ();
+
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = ();
+        type ParseResponseError = IoError;
+
+        fn render_request(
+            &self,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::builder()
+                .method(Method::POST)
+                .body(vec![])
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            _response: Response<Body>,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Result<Self::ParseResponseOutput, Self::RetryReason>, Self::ParseResponseError>
+        {
+            Ok(Ok(()))
+        }
+
+        fn retry_on_respond_failed(
+            &self,
+            _error: &(dyn std::error::Error + Send + Sync + 'static),
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Option<Self::RetryReason> {
+            Some(())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MyFlakyClient {
+        idempotency_keys: Mutex<Vec<Option<HeaderValue>>>,
+    }
+    #[async_trait]
+    impl Client for MyFlakyClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let mut idempotency_keys = self.idempotency_keys.lock().unwrap();
+            idempotency_keys.push(request.headers().get(IDEMPOTENCY_KEY).cloned());
+            if idempotency_keys.len() == 1 {
+                Err(IoError::other("connection reset"))
+            } else {
+                Ok(Response::new(vec![]))
+            }
+        }
+    }
+    #[async_trait]
+    impl RetryableClient for MyFlakyClient {
+        async fn sleep(&self, _dur: Duration) {}
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let client = MyFlakyClient::default();
+        block_on(client.respond_endpoint_until_done(&MyCreateEndpoint)).unwrap();
+        let idempotency_keys = client.idempotency_keys.lock().unwrap();
+        assert_eq!(idempotency_keys.len(), 2);
+        assert!(idempotency_keys[0].is_some());
+        assert_eq!(idempotency_keys[0], idempotency_keys[1]);
+
+        let client = MyFlakyClient::default();
+        let ret = block_on(client.respond_endpoint_until_done_with_callback(
+            &MyCreateEndpoint,
+            |mut req, _| {
+                req.headers_mut().remove(IDEMPOTENCY_KEY);
+                req
+            },
+            |_, _| {},
+        ));
+        assert!(matches!(
+            ret,
+            Err(RetryableClientRespondEndpointUntilDoneError::RespondFailed(
+                _
+            ))
+        ));
+        assert_eq!(client.idempotency_keys.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_sleeper_client() {
+        let sleeper = sleep::VirtualClockSleeper::new();
+        let client = SleeperClient::new(MyFlakyClient::default(), sleeper.clone());
+        block_on(client.respond_endpoint_until_done(&MyCreateEndpoint)).unwrap();
+        assert_eq!(sleeper.sleeps(), vec![Duration::from_millis(500)]);
+        assert_eq!(sleeper.elapsed(), Duration::from_millis(500));
+    }
+
+    #[derive(Clone)]
+    struct MyTimeoutEndpoint;
+    impl Endpoint for MyTimeoutEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = Option<RequestTimeout>;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::new(vec![]))
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(response.extensions().get::<RequestTimeout>().copied())
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            Some(Duration::from_secs(2))
+        }
+    }
+
+    struct MyEchoExtensionsClient;
+    #[async_trait]
+    impl Client for MyEchoExtensionsClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let mut res = Response::new(vec![]);
+            *res.extensions_mut() = request.extensions().to_owned();
+            Ok(res)
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(
+            block_on(MyEchoExtensionsClient.respond_endpoint(&MyTimeoutEndpoint)).unwrap(),
+            Some(RequestTimeout(Duration::from_secs(2)))
+        );
+    }
+
+    struct MyPendingClient;
+    #[async_trait]
+    impl Client for MyPendingClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            _request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            core::future::pending().await
+        }
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let err = block_on(
+            MyPendingClient
+                .respond_endpoint_with_cancellation(&MyTimeoutEndpoint, core::future::ready(())),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ClientRespondEndpointError::Cancelled));
+        assert_eq!(
+            err.with_endpoint(MyTimeoutEndpoint.name()).to_string(),
+            "http_api_client::tests::MyTimeoutEndpoint: Cancelled"
+        );
+    }
 }
@@ -0,0 +1,419 @@
+use core::{
+    fmt,
+    hash::{BuildHasher as _, Hasher as _},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::{
+    collections::hash_map::RandomState,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::HOST,
+        uri::{Authority, Parts, Scheme},
+        Uri,
+    },
+    Body, Request, Response,
+};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancingStrategy {
+    #[default]
+    RoundRobin,
+    LeastInFlight,
+    /// The one with fewer requests in flight of two picked at random.
+    PowerOfTwoChoices,
+}
+
+/// Sends each request to one of `upstreams`, replacing the scheme and authority of its URI.
+///
+/// An upstream is skipped for `cooldown` after a transport error or a server error response,
+/// so that a retry, e.g. by `respond_endpoint_until_done`, goes to another one.
+/// When all of them are in cooldown, all of them are candidates again.
+#[derive(Debug, Clone)]
+pub struct LoadBalancingClient<C> {
+    pub client: C,
+    pub strategy: LoadBalancingStrategy,
+    pub cooldown: Duration,
+    upstreams: Arc<[Upstream]>,
+    next: Arc<AtomicUsize>,
+    random_state: Arc<AtomicU64>,
+}
+
+#[derive(Debug)]
+struct Upstream {
+    uri: Uri,
+    in_flight: AtomicUsize,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Upstream {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until
+            .lock()
+            .expect("poisoned")
+            .is_none_or(|x| x <= now)
+    }
+
+    fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+}
+
+struct InFlightGuard<'a>(&'a Upstream);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<C> LoadBalancingClient<C> {
+    /// `upstreams` are base URLs like `https://eu.api.example.com`, only their scheme and authority are used.
+    pub fn new(client: C, upstreams: impl IntoIterator<Item = Uri>) -> Self {
+        Self {
+            client,
+            strategy: Default::default(),
+            cooldown: Duration::from_secs(10),
+            upstreams: upstreams
+                .into_iter()
+                .map(|uri| Upstream {
+                    uri,
+                    in_flight: AtomicUsize::new(0),
+                    unhealthy_until: Mutex::new(None),
+                })
+                .collect(),
+            next: Default::default(),
+            // Only seeded from the OS once, xorshift is plenty for spreading requests.
+            random_state: Arc::new(AtomicU64::new(
+                RandomState::new().build_hasher().finish() | 1,
+            )),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: LoadBalancingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// The upstreams not in cooldown.
+    pub fn healthy_upstreams(&self) -> Vec<Uri> {
+        let now = Instant::now();
+        self.upstreams
+            .iter()
+            .filter(|x| x.is_healthy(now))
+            .map(|x| x.uri.to_owned())
+            .collect()
+    }
+
+    fn random(&self) -> u64 {
+        let xorshift = |mut x: u64| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let prev = self
+            .random_state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(xorshift(x)))
+            .expect("always updated");
+        xorshift(prev)
+    }
+
+    fn pick(&self) -> Option<&Upstream> {
+        let now = Instant::now();
+        let mut candidates = self
+            .upstreams
+            .iter()
+            .filter(|x| x.is_healthy(now))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            candidates = self.upstreams.iter().collect();
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let len = candidates.len();
+        let index = match self.strategy {
+            LoadBalancingStrategy::RoundRobin => self.next.fetch_add(1, Ordering::SeqCst) % len,
+            // Starting where round robin is, so that ties are spread.
+            LoadBalancingStrategy::LeastInFlight => {
+                let offset = self.next.fetch_add(1, Ordering::SeqCst);
+                (0..len)
+                    .map(|i| (offset + i) % len)
+                    .min_by_key(|&i| candidates[i].in_flight())
+                    .unwrap_or(0)
+            }
+            LoadBalancingStrategy::PowerOfTwoChoices if len == 1 => 0,
+            LoadBalancingStrategy::PowerOfTwoChoices => {
+                let random = self.random() as usize;
+                // `b` is one of the other `len - 1` candidates.
+                let a = random % len;
+                let b = (a + 1 + (random / len) % (len - 1)) % len;
+                if candidates[b].in_flight() < candidates[a].in_flight() {
+                    b
+                } else {
+                    a
+                }
+            }
+        };
+        Some(candidates[index])
+    }
+}
+
+pub(crate) fn rewrite_uri(
+    uri: &Uri,
+    scheme: Option<&Scheme>,
+    authority: &Authority,
+) -> Option<Uri> {
+    let mut parts = Parts::from(uri.to_owned());
+    parts.scheme = scheme.cloned().or(parts.scheme);
+    parts.authority = Some(authority.to_owned());
+    if parts.path_and_query.is_none() {
+        parts.path_and_query = Some("/".parse().ok()?);
+    }
+    Uri::from_parts(parts).ok()
+}
+
+#[async_trait]
+impl<C> Client for LoadBalancingClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = LoadBalancingClientRespondError<C::RespondError>;
+
+    async fn respond(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, Self::RespondError> {
+        let upstream = self
+            .pick()
+            .ok_or(LoadBalancingClientRespondError::NoUpstreams)?;
+        *request.uri_mut() = upstream
+            .uri
+            .authority()
+            .and_then(|authority| rewrite_uri(request.uri(), upstream.uri.scheme(), authority))
+            .ok_or_else(|| {
+                LoadBalancingClientRespondError::InvalidUpstream(upstream.uri.to_owned())
+            })?;
+        request.headers_mut().remove(HOST);
+
+        upstream.in_flight.fetch_add(1, Ordering::SeqCst);
+        let _guard = InFlightGuard(upstream);
+
+        let ret = self.client.respond(request).await;
+
+        let failed = ret.as_ref().map_or(true, |x| x.status().is_server_error());
+        *upstream.unhealthy_until.lock().expect("poisoned") =
+            failed.then(|| Instant::now() + self.cooldown);
+
+        ret.map_err(LoadBalancingClientRespondError::RespondFailed)
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for LoadBalancingClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum LoadBalancingClientRespondError<E> {
+    RespondFailed(E),
+    NoUpstreams,
+    InvalidUpstream(Uri),
+}
+impl<E> fmt::Display for LoadBalancingClientRespondError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E> std::error::Error for LoadBalancingClientRespondError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::NoUpstreams | Self::InvalidUpstream(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::{
+        future::{poll_fn, Future},
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::{
+        http::StatusCode, Endpoint, RetryableEndpoint, RetryableEndpointRetry,
+    };
+
+    use crate::{sleep::VirtualClockSleeper, RetryableClient as _};
+
+    // `a.test` answers with 503, `slow.test` never does, the others with their URI.
+    #[derive(Debug, Default)]
+    struct MyClient {
+        sleeper: VirtualClockSleeper,
+    }
+    #[async_trait]
+    impl Client for MyClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let mut res = Response::new(request.uri().to_string().into_bytes());
+            match request.uri().host() {
+                Some("a.test") => *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE,
+                Some("slow.test") => poll_fn(|_| Poll::<()>::Pending).await,
+                _ => {}
+            }
+            Ok(res)
+        }
+    }
+    impl WithSleeper for MyClient {
+        fn sleeper(&self) -> &dyn Sleeper {
+            &self.sleeper
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint;
+    impl RetryableEndpoint for MyEndpoint {
+        type RetryReason = StatusCode;
+
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = String;
+        type ParseResponseError = IoError;
+
+        fn render_request(
+            &self,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::get("http://placeholder/x?y=1")
+                .header(HOST, "placeholder")
+                .body(vec![])
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+            _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+        ) -> Result<Result<Self::ParseResponseOutput, Self::RetryReason>, Self::ParseResponseError>
+        {
+            if response.status().is_success() {
+                Ok(Ok(String::from_utf8(response.into_body()).unwrap()))
+            } else {
+                Ok(Err(response.status()))
+            }
+        }
+    }
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = String;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            RetryableEndpoint::render_request(self, None)
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(String::from_utf8(response.into_body()).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_failover() {
+        let client = LoadBalancingClient::new(
+            MyClient::default(),
+            [
+                "https://a.test".parse().unwrap(),
+                "https://b.test:8443".parse().unwrap(),
+            ],
+        );
+
+        assert_eq!(
+            block_on(client.respond_endpoint_until_done(&MyEndpoint)).unwrap(),
+            "https://b.test:8443/x?y=1"
+        );
+        assert_eq!(
+            client.healthy_upstreams(),
+            vec!["https://b.test:8443".parse::<Uri>().unwrap()]
+        );
+        assert_eq!(
+            block_on(client.respond_endpoint_until_done(&MyEndpoint)).unwrap(),
+            "https://b.test:8443/x?y=1"
+        );
+    }
+
+    #[test]
+    fn test_in_flight() {
+        for strategy in [
+            LoadBalancingStrategy::LeastInFlight,
+            LoadBalancingStrategy::PowerOfTwoChoices,
+        ] {
+            let client = LoadBalancingClient::new(
+                MyClient::default(),
+                [
+                    "http://slow.test".parse().unwrap(),
+                    "http://c.test".parse().unwrap(),
+                ],
+            )
+            .with_strategy(strategy);
+
+            let mut slow = pin!(client.respond_endpoint(&MyEndpoint));
+            while slow
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_ready()
+            {
+                slow.set(client.respond_endpoint(&MyEndpoint));
+            }
+
+            for _ in 0..20 {
+                assert_eq!(
+                    block_on(client.respond_endpoint(&MyEndpoint)).unwrap(),
+                    "http://c.test/x?y=1"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use core::{fmt, time::Duration};
+
+use http_api_client_endpoint::http::{Method, StatusCode};
+
+pub trait ClientMetrics: fmt::Debug + Send + Sync {
+    /// `status` is `None` when [`Client::respond`](crate::Client::respond) failed.
+    fn record_request(
+        &self,
+        endpoint: &str,
+        method: &Method,
+        status: Option<StatusCode>,
+        latency: Duration,
+    );
+
+    fn record_retry(&self, endpoint: &str, retry_count: usize);
+
+    fn record_give_up(&self, endpoint: &str);
+}
+
+pub fn status_class(status: Option<StatusCode>) -> &'static str {
+    match status.map(|x| x.as_u16()) {
+        Some(100..=199) => "1xx",
+        Some(200..=299) => "2xx",
+        Some(300..=399) => "3xx",
+        Some(400..=499) => "4xx",
+        Some(500..=599) => "5xx",
+        Some(_) => "unknown",
+        None => "error",
+    }
+}
+
+//
+#[cfg(feature = "metrics")]
+pub use self::metrics_rs::MetricsRsClientMetrics;
+
+#[cfg(feature = "metrics")]
+mod metrics_rs {
+    use super::*;
+
+    use std::borrow::Cow;
+
+    /// Reports to the global recorder of the `metrics` crate.
+    ///
+    /// Metrics: `{prefix}_request_duration_seconds` (histogram), `{prefix}_requests_total`,
+    /// `{prefix}_retries_total` and `{prefix}_give_ups_total` (counters),
+    /// all labeled with `endpoint`.
+    #[derive(Debug, Clone)]
+    pub struct MetricsRsClientMetrics {
+        prefix: Cow<'static, str>,
+    }
+
+    impl Default for MetricsRsClientMetrics {
+        fn default() -> Self {
+            Self::new("http_api_client")
+        }
+    }
+
+    impl MetricsRsClientMetrics {
+        pub fn new(prefix: impl Into<Cow<'static, str>>) -> Self {
+            Self {
+                prefix: prefix.into(),
+            }
+        }
+    }
+
+    impl ClientMetrics for MetricsRsClientMetrics {
+        fn record_request(
+            &self,
+            endpoint: &str,
+            method: &Method,
+            status: Option<StatusCode>,
+            latency: Duration,
+        ) {
+            let labels = [
+                ("endpoint", endpoint.to_owned()),
+                ("method", method.as_str().to_owned()),
+                ("status_class", status_class(status).to_owned()),
+            ];
+
+            ::metrics::histogram!(format!("{}_request_duration_seconds", self.prefix), &labels)
+                .record(latency.as_secs_f64());
+            ::metrics::counter!(format!("{}_requests_total", self.prefix), &labels).increment(1);
+        }
+
+        fn record_retry(&self, endpoint: &str, _retry_count: usize) {
+            ::metrics::counter!(
+                format!("{}_retries_total", self.prefix),
+                "endpoint" => endpoint.to_owned()
+            )
+            .increment(1);
+        }
+
+        fn record_give_up(&self, endpoint: &str) {
+            ::metrics::counter!(
+                format!("{}_give_ups_total", self.prefix),
+                "endpoint" => endpoint.to_owned()
+            )
+            .increment(1);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use core::fmt;
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{
+    http::{
+        header::{
+            AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, LOCATION,
+            PROXY_AUTHORIZATION,
+        },
+        Method, StatusCode, Uri,
+    },
+    Body, RedirectPolicy, Request, Response,
+};
+
+use crate::{Client, ClientMetrics, Sleeper, WithSleeper};
+
+/// Follows redirects as asked by the [`RedirectPolicy`] extension, falling back to `default`.
+///
+/// The wrapped client must not follow redirects itself.
+/// `Authorization`, `Cookie` and `Proxy-Authorization` are dropped when a redirect leaves the origin.
+#[derive(Debug, Clone)]
+pub struct RedirectClient<C> {
+    pub client: C,
+    pub default: RedirectPolicy,
+}
+
+impl<C> RedirectClient<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            default: RedirectPolicy::Limit(10),
+        }
+    }
+
+    pub fn with_default(mut self, policy: RedirectPolicy) -> Self {
+        self.default = policy;
+        self
+    }
+}
+
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.split('#').next().unwrap_or_default();
+    let scheme = base.scheme_str()?;
+    let authority = base.authority()?;
+
+    let has_scheme = location.split_once(':').is_some_and(|(x, _)| {
+        x.starts_with(|c: char| c.is_ascii_alphabetic())
+            && x.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+
+    let uri = if has_scheme {
+        location.to_owned()
+    } else if location.starts_with("//") {
+        format!("{scheme}:{location}")
+    } else if location.starts_with('/') {
+        format!("{scheme}://{authority}{location}")
+    } else if location.starts_with('?') {
+        format!("{scheme}://{authority}{}{location}", base.path())
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!(
+            "{scheme}://{authority}/{}{location}",
+            dir.trim_start_matches('/')
+        )
+    };
+
+    uri.parse().ok()
+}
+
+fn origin(uri: &Uri) -> (Option<&str>, Option<&str>, Option<u16>) {
+    let port = uri.port_u16().or(match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    });
+    (uri.scheme_str(), uri.host(), port)
+}
+
+fn redirect_request(
+    request: Request<Body>,
+    status: StatusCode,
+    location: Uri,
+    cross_origin: bool,
+) -> Request<Body> {
+    let (mut head, mut body) = request.into_parts();
+
+    if (status == StatusCode::SEE_OTHER && head.method != Method::HEAD)
+        || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+            && head.method == Method::POST)
+    {
+        head.method = Method::GET;
+        body = Body::new();
+        for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+            head.headers.remove(name);
+        }
+    }
+
+    head.headers.remove(HOST);
+    if cross_origin {
+        for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION] {
+            head.headers.remove(name);
+        }
+    }
+    head.uri = location;
+
+    Request::from_parts(head, body)
+}
+
+#[async_trait]
+impl<C> Client for RedirectClient<C>
+where
+    C: Client + Send + Sync,
+{
+    type RespondError = RedirectClientRespondError<C::RespondError>;
+
+    async fn respond(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, Self::RespondError> {
+        // Taken out so that a backend which rejects per-request redirect policies never sees it.
+        let policy = request
+            .extensions_mut()
+            .remove::<RedirectPolicy>()
+            .unwrap_or(self.default);
+        let (limit, same_origin_only) = match policy {
+            RedirectPolicy::None => {
+                return self
+                    .client
+                    .respond(request)
+                    .await
+                    .map_err(RedirectClientRespondError::RespondFailed)
+            }
+            RedirectPolicy::Limit(limit) => (limit, false),
+            RedirectPolicy::SameOrigin(limit) => (limit, true),
+        };
+
+        let mut count = 0;
+        loop {
+            let next = request.clone();
+            let res = self
+                .client
+                .respond(request)
+                .await
+                .map_err(RedirectClientRespondError::RespondFailed)?;
+
+            if !matches!(
+                res.status(),
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            ) {
+                return Ok(res);
+            }
+            let Some(location) = res.headers().get(LOCATION) else {
+                return Ok(res);
+            };
+            let location = location
+                .to_str()
+                .ok()
+                .and_then(|x| resolve_location(next.uri(), x))
+                .ok_or_else(|| {
+                    RedirectClientRespondError::InvalidLocation(
+                        String::from_utf8_lossy(location.as_bytes()).into_owned(),
+                    )
+                })?;
+
+            let cross_origin = origin(next.uri()) != origin(&location);
+            if cross_origin && same_origin_only {
+                return Ok(res);
+            }
+            if count >= limit {
+                return Err(RedirectClientRespondError::TooManyRedirects { limit });
+            }
+            count += 1;
+
+            request = redirect_request(next, res.status(), location, cross_origin);
+        }
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C> WithSleeper for RedirectClient<C>
+where
+    C: WithSleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.client.sleeper()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum RedirectClientRespondError<E> {
+    RespondFailed(E),
+    InvalidLocation(String),
+    TooManyRedirects { limit: usize },
+}
+impl<E> fmt::Display for RedirectClientRespondError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl<E> std::error::Error for RedirectClientRespondError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RespondFailed(err) => Some(err),
+            Self::InvalidLocation(_) | Self::TooManyRedirects { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Error as IoError;
+
+    use futures_executor::block_on;
+    use http_api_client_endpoint::Endpoint;
+
+    // `a.test/start` -> `a.test/next` -> `b.test/final`, which echoes the method and credentials.
+    #[derive(Debug)]
+    struct MyRedirectingClient;
+    #[async_trait]
+    impl Client for MyRedirectingClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let (status, location) = match request.uri().to_string().as_str() {
+                "http://a.test/start" => (StatusCode::FOUND, "next"),
+                "http://a.test/next" => (StatusCode::TEMPORARY_REDIRECT, "//b.test/final"),
+                _ => {
+                    let body = format!(
+                        "{} {} {:?}",
+                        request.uri(),
+                        request.method(),
+                        request.headers().get(AUTHORIZATION)
+                    );
+                    return Ok(Response::new(body.into_bytes()));
+                }
+            };
+            let mut res = Response::new(Body::new());
+            *res.status_mut() = status;
+            res.headers_mut()
+                .insert(LOCATION, location.parse().unwrap());
+            Ok(res)
+        }
+    }
+
+    #[derive(Clone)]
+    struct MyEndpoint(Option<RedirectPolicy>);
+    impl Endpoint for MyEndpoint {
+        type RenderRequestError = IoError;
+
+        type ParseResponseOutput = Response<Body>;
+        type ParseResponseError = IoError;
+
+        fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+            Ok(Request::post("http://a.test/start")
+                .header(AUTHORIZATION, "secret")
+                .body(b"ping".to_vec())
+                .unwrap())
+        }
+
+        fn parse_response(
+            &self,
+            response: Response<Body>,
+        ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+            Ok(response)
+        }
+
+        fn redirect_policy(&self) -> Option<RedirectPolicy> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_follow() {
+        let client = RedirectClient::new(MyRedirectingClient);
+
+        let res = block_on(client.respond_endpoint(&MyEndpoint(None))).unwrap();
+        assert_eq!(res.body(), b"http://b.test/final GET None");
+
+        let res =
+            block_on(client.respond_endpoint(&MyEndpoint(Some(RedirectPolicy::None)))).unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND);
+
+        let res =
+            block_on(client.respond_endpoint(&MyEndpoint(Some(RedirectPolicy::SameOrigin(10)))))
+                .unwrap();
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "//b.test/final");
+
+        assert!(matches!(
+            block_on(client.respond_endpoint(&MyEndpoint(Some(RedirectPolicy::Limit(1))))),
+            Err(crate::ClientRespondEndpointError::RespondFailed(
+                RedirectClientRespondError::TooManyRedirects { limit: 1 }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let base: Uri = "https://a.test/x/y?q=1".parse().unwrap();
+        for (location, expected) in [
+            ("http://b.test/z", "http://b.test/z"),
+            ("//b.test/z", "https://b.test/z"),
+            ("/z#frag", "https://a.test/z"),
+            ("?q=2", "https://a.test/x/y?q=2"),
+            ("z", "https://a.test/x/z"),
+        ] {
+            assert_eq!(resolve_location(&base, location).unwrap(), expected);
+        }
+    }
+}
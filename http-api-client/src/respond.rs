@@ -0,0 +1,153 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use http_api_client_endpoint::{
+    http::{HeaderValue, Method},
+    Body, Endpoint, EndpointMetadata, Idempotent, MaxResponseBodySize, RedirectPolicy, Request,
+    RequestCompression, RequestTimeout, Response, RetryableEndpoint, RetryableEndpointRetry,
+};
+
+#[cfg(feature = "tracing")]
+use crate::instrumentation;
+use crate::{ClientMetrics, IDEMPOTENCY_KEY};
+
+/// What an endpoint asks of each of its requests.
+pub(crate) struct EndpointOptions {
+    pub(crate) metadata: EndpointMetadata,
+    timeout: Option<Duration>,
+    compression: Option<RequestCompression>,
+    max_response_body_size: Option<usize>,
+    redirect_policy: Option<RedirectPolicy>,
+}
+
+impl EndpointOptions {
+    pub(crate) fn of<EP>(endpoint: &EP) -> Self
+    where
+        EP: Endpoint + ?Sized,
+    {
+        Self {
+            metadata: endpoint.metadata(),
+            timeout: endpoint.timeout(),
+            compression: endpoint.compression(),
+            max_response_body_size: endpoint.max_response_body_size(),
+            redirect_policy: endpoint.redirect_policy(),
+        }
+    }
+
+    pub(crate) fn of_retryable<EP>(endpoint: &EP) -> Self
+    where
+        EP: RetryableEndpoint + ?Sized,
+    {
+        Self {
+            metadata: endpoint.metadata(),
+            timeout: endpoint.timeout(),
+            compression: endpoint.compression(),
+            max_response_body_size: endpoint.max_response_body_size(),
+            redirect_policy: endpoint.redirect_policy(),
+        }
+    }
+}
+
+/// Sets the request extensions asked for by the endpoint, returning whether the request is idempotent.
+pub(crate) fn prepare_request(options: &EndpointOptions, request: &mut Request<Body>) -> bool {
+    set_request_extension(request, options.timeout.map(RequestTimeout));
+    set_request_extension(request, options.compression);
+    set_request_extension(
+        request,
+        options.max_response_body_size.map(MaxResponseBodySize),
+    );
+    set_request_extension(request, options.redirect_policy);
+
+    let idempotent = options.metadata.is_idempotent(request.method());
+    set_request_extension(request, idempotent.then_some(Idempotent));
+    idempotent
+}
+
+/// Doesn't override an extension set by the endpoint itself.
+fn set_request_extension<T>(request: &mut Request<Body>, extension: Option<T>)
+where
+    T: Clone + Send + Sync + 'static,
+{
+    if let Some(extension) = extension {
+        if request.extensions().get::<T>().is_none() {
+            request.extensions_mut().insert(extension);
+        }
+    }
+}
+
+/// Reuses `idempotency_key` across attempts, generating it on first use.
+pub(crate) fn set_idempotency_key(
+    request: &mut Request<Body>,
+    idempotency_key: &mut Option<HeaderValue>,
+) {
+    if !request.headers().contains_key(IDEMPOTENCY_KEY) {
+        let value = idempotency_key.get_or_insert_with(|| {
+            HeaderValue::from_str(&uuid::Uuid::new_v4().to_string())
+                .expect("uuid is a valid header value")
+        });
+        request
+            .headers_mut()
+            .insert(IDEMPOTENCY_KEY, value.to_owned());
+    }
+}
+
+pub(crate) fn record_request<E>(
+    metrics: Option<&dyn ClientMetrics>,
+    endpoint_name: &str,
+    method: &Method,
+    started_at: Instant,
+    ret: &Result<Response<Body>, E>,
+) {
+    if let Some(metrics) = metrics {
+        metrics.record_request(
+            endpoint_name,
+            method,
+            ret.as_ref().ok().map(|x| x.status()),
+            started_at.elapsed(),
+        );
+    }
+}
+
+/// Counts a failed attempt, returning how long to wait before the next one,
+/// `None` once the endpoint's `max_retry_count` is reached.
+pub(crate) fn next_retry<EP>(
+    endpoint: &EP,
+    endpoint_name: &str,
+    metrics: Option<&dyn ClientMetrics>,
+    retry: &mut Option<RetryableEndpointRetry<EP::RetryReason>>,
+    reason: EP::RetryReason,
+) -> Option<Duration>
+where
+    EP: RetryableEndpoint + ?Sized,
+{
+    let retry = match retry {
+        Some(retry) => {
+            retry.count += 1;
+            retry.reason = reason;
+            retry
+        }
+        None => retry.insert(RetryableEndpointRetry::new(1, reason)),
+    };
+
+    if retry.count >= endpoint.max_retry_count() {
+        if let Some(metrics) = metrics {
+            metrics.record_give_up(endpoint_name);
+        }
+        return None;
+    }
+
+    let dur = endpoint.next_retry_in(retry);
+
+    if let Some(metrics) = metrics {
+        metrics.record_retry(endpoint_name, retry.count);
+    }
+
+    #[cfg(feature = "tracing")]
+    instrumentation::record_retry(
+        retry.count,
+        dur,
+        endpoint.retry_reason_label(&retry.reason).as_deref(),
+    );
+
+    Some(dur)
+}
@@ -0,0 +1,145 @@
+use core::{fmt, time::Duration};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use http_api_client_endpoint::{Body, Request, Response};
+
+use crate::{Client, ClientMetrics, RetryableClient};
+
+#[async_trait]
+pub trait Sleeper: fmt::Debug + Send + Sync {
+    async fn sleep(&self, dur: Duration);
+}
+
+#[async_trait]
+impl<S> Sleeper for Arc<S>
+where
+    S: Sleeper + ?Sized,
+{
+    async fn sleep(&self, dur: Duration) {
+        (**self).sleep(dur).await
+    }
+}
+
+#[async_trait]
+impl<S> Sleeper for Box<S>
+where
+    S: Sleeper + ?Sized,
+{
+    async fn sleep(&self, dur: Duration) {
+        (**self).sleep(dur).await
+    }
+}
+
+/// Any [`Client`] implementing this is a [`RetryableClient`].
+pub trait WithSleeper {
+    fn sleeper(&self) -> &dyn Sleeper;
+}
+
+#[async_trait]
+impl<C> RetryableClient for C
+where
+    C: Client + WithSleeper + Sync,
+{
+    async fn sleep(&self, dur: Duration) {
+        self.sleeper().sleep(dur).await
+    }
+}
+
+//
+/// Pairs a [`Client`] with a [`Sleeper`], making it a [`RetryableClient`].
+#[derive(Debug, Clone)]
+pub struct SleeperClient<C, S = Arc<dyn Sleeper>> {
+    pub client: C,
+    pub sleeper: S,
+}
+
+impl<C, S> SleeperClient<C, S> {
+    pub fn new(client: C, sleeper: S) -> Self {
+        Self { client, sleeper }
+    }
+}
+
+#[async_trait]
+impl<C, S> Client for SleeperClient<C, S>
+where
+    C: Client + Send + Sync,
+    S: Send + Sync,
+{
+    type RespondError = C::RespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        self.client.respond(request).await
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.client.metrics()
+    }
+}
+
+impl<C, S> WithSleeper for SleeperClient<C, S>
+where
+    S: Sleeper,
+{
+    fn sleeper(&self) -> &dyn Sleeper {
+        &self.sleeper
+    }
+}
+
+//
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSleeper;
+
+#[cfg(feature = "tokio")]
+#[async_trait]
+impl Sleeper for TokioSleeper {
+    async fn sleep(&self, dur: Duration) {
+        tokio::time::sleep(dur).await
+    }
+}
+
+/// Drives its timers on its own thread, so it works under any executor.
+#[cfg(feature = "async-io")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncIoSleeper;
+
+#[cfg(feature = "async-io")]
+#[async_trait]
+impl Sleeper for AsyncIoSleeper {
+    async fn sleep(&self, dur: Duration) {
+        async_io::Timer::after(dur).await;
+    }
+}
+
+/// smol's timers are the ones of async-io.
+#[cfg(feature = "async-io")]
+pub type SmolSleeper = AsyncIoSleeper;
+
+//
+/// Returns immediately, only advancing a virtual clock. Useful in tests.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClockSleeper {
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl VirtualClockSleeper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.sleeps.lock().expect("poisoned").iter().sum()
+    }
+
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().expect("poisoned").to_owned()
+    }
+}
+
+#[async_trait]
+impl Sleeper for VirtualClockSleeper {
+    async fn sleep(&self, dur: Duration) {
+        self.sleeps.lock().expect("poisoned").push(dur);
+    }
+}
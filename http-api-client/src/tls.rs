@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use p12_keystore::KeyStore;
+pub use rustls;
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    crypto::ring::default_provider,
+    pki_types::{pem::PemObject as _, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    server::ParsedCertificate,
+    CertificateError, DigitallySignedStruct, Error as RustlsError, RootCertStore, SignatureScheme,
+};
+
+use crate::{
+    config::{read_file, FromConfigError, IdentityConfig, TlsVersion},
+    ClientConfig, VersionPreference,
+};
+
+/// The rustls config for the TLS settings of `config`, with the ring provider and the webpki roots as built-in roots.
+///
+/// ALPN follows `http_version`.
+pub fn rustls_client_config<E>(
+    config: &ClientConfig,
+) -> Result<rustls::ClientConfig, FromConfigError<E>> {
+    let provider = Arc::new(default_provider());
+
+    let mut root_store = RootCertStore::empty();
+    if config.built_in_root_certificates {
+        root_store
+            .roots
+            .extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+    for (path, pem) in config
+        .root_certificates
+        .iter()
+        .zip(config.read_root_certificates()?)
+    {
+        let invalid = || FromConfigError::InvalidCertificate(path.to_owned());
+        for certificate in CertificateDer::pem_slice_iter(&pem) {
+            root_store
+                .add(certificate.map_err(|_| invalid())?)
+                .map_err(|_| invalid())?;
+        }
+    }
+    let verifier =
+        WebPkiServerVerifier::builder_with_provider(Arc::new(root_store), provider.clone())
+            .build()
+            .map_err(|err| FromConfigError::InvalidTlsConfig(err.to_string()))?;
+
+    let pins = config
+        .pinned_public_keys
+        .iter()
+        .map(|pin| {
+            STANDARD
+                .decode(pin.strip_prefix("sha256//").unwrap_or(pin))
+                .ok()
+                .filter(|x| x.len() == 32)
+                .ok_or_else(|| FromConfigError::InvalidPinnedPublicKey(pin.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let versions: &[&rustls::SupportedProtocolVersion] = match config.min_tls_version {
+        Some(TlsVersion::Tls1_3) => &[&rustls::version::TLS13],
+        Some(TlsVersion::Tls1_2) | None => rustls::DEFAULT_VERSIONS,
+    };
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_protocol_versions(versions)
+        .map_err(|err| FromConfigError::InvalidTlsConfig(err.to_string()))?;
+    let builder = if pins.is_empty() {
+        builder.with_webpki_verifier(verifier)
+    } else {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedPublicKeyVerifier {
+                inner: verifier,
+                pins,
+            }))
+    };
+
+    let mut tls_config = match &config.identity {
+        Some(IdentityConfig::Pem { cert, key }) => {
+            let certificates = CertificateDer::pem_slice_iter(&read_file(cert)?)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| FromConfigError::InvalidCertificate(cert.to_owned()))?;
+            let private_key = PrivateKeyDer::from_pem_slice(&read_file(key)?)
+                .map_err(|_| FromConfigError::InvalidCertificate(key.to_owned()))?;
+            builder
+                .with_client_auth_cert(certificates, private_key)
+                .map_err(|err| FromConfigError::InvalidTlsConfig(err.to_string()))?
+        }
+        Some(IdentityConfig::Pkcs12 { path, password }) => {
+            let invalid = || FromConfigError::InvalidCertificate(path.to_owned());
+            let key_store =
+                KeyStore::from_pkcs12(&read_file(path)?, password).map_err(|_| invalid())?;
+            let (_, key_chain) = key_store.private_key_chain().ok_or_else(invalid)?;
+            let certificates = key_chain
+                .chain()
+                .iter()
+                .map(|x| CertificateDer::from(x.as_der().to_vec()))
+                .collect();
+            let private_key =
+                PrivateKeyDer::try_from(key_chain.key().to_vec()).map_err(|_| invalid())?;
+            builder
+                .with_client_auth_cert(certificates, private_key)
+                .map_err(|err| FromConfigError::InvalidTlsConfig(err.to_string()))?
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    tls_config.alpn_protocols = match config.http_version {
+        Some(VersionPreference::Http1Only) => vec![b"http/1.1".to_vec()],
+        Some(VersionPreference::Http2PriorKnowledge) => vec![b"h2".to_vec()],
+        None => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+    };
+
+    Ok(tls_config)
+}
+
+/// The pin of a DER encoded SubjectPublicKeyInfo, in the format taken by `pinned_public_keys`.
+pub fn public_key_pin(spki_der: &[u8]) -> String {
+    format!(
+        "sha256//{}",
+        STANDARD.encode(ring::digest::digest(&ring::digest::SHA256, spki_der))
+    )
+}
+
+//
+/// Only pins the leaf certificate, unlike HPKP which accepts a pin anywhere in the chain.
+#[derive(Debug)]
+struct PinnedPublicKeyVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<Vec<u8>>,
+}
+
+impl ServerCertVerifier for PinnedPublicKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, RustlsError> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        let spki = ParsedCertificate::try_from(end_entity)?.subject_public_key_info();
+        let hash = ring::digest::digest(&ring::digest::SHA256, spki.as_ref());
+        if self.pins.iter().any(|pin| pin == hash.as_ref()) {
+            Ok(verified)
+        } else {
+            Err(RustlsError::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, RustlsError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rustls_client_config() {
+        let tls_config = rustls_client_config::<()>(
+            &ClientConfig::new().with_http_version(VersionPreference::Http1Only),
+        )
+        .unwrap();
+        assert_eq!(tls_config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        assert!(matches!(
+            rustls_client_config::<()>(&ClientConfig::new().with_pinned_public_key("sha256//AAAA")),
+            Err(FromConfigError::InvalidPinnedPublicKey(_))
+        ));
+        assert!(matches!(
+            rustls_client_config::<()>(&ClientConfig::new().without_built_in_root_certificates()),
+            Err(FromConfigError::InvalidTlsConfig(_))
+        ));
+        assert!(matches!(
+            rustls_client_config::<()>(&ClientConfig::new().with_identity(
+                IdentityConfig::Pkcs12 {
+                    path: "Cargo.toml".into(),
+                    password: "".into()
+                }
+            )),
+            Err(FromConfigError::InvalidCertificate(_))
+        ));
+
+        assert_eq!(
+            public_key_pin(b""),
+            "sha256//47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+    }
+}
@@ -0,0 +1,367 @@
+pub use http_api_client;
+pub use hyper;
+pub use hyper_util;
+
+use core::time::Duration;
+use std::{sync::Arc, time::Instant};
+
+use bytes::Bytes;
+use http_api_client::{
+    async_trait,
+    config::FromConfigError,
+    http::{header::USER_AGENT, HeaderMap, HeaderValue},
+    sleep::TokioSleeper,
+    Body, ClientConfig, ClientMetrics, EffectiveUri, MaxResponseBodySize, RedirectPolicy,
+    RemoteAddr, Request, RequestProxy, RequestTimeout, Response, ResponseTimings, Sleeper,
+    TlsIdentity, VersionPreference, WithSleeper,
+};
+pub use http_api_client::{Client, RetryableClient};
+use http_body_util::{BodyExt as _, Full, LengthLimitError, Limited};
+use hyper::Error as HyperError;
+use hyper_util::{
+    client::legacy::{
+        connect::{Connect, HttpConnector, HttpInfo},
+        Client as HyperHttpClient, Error as HyperHttpClientError,
+    },
+    rt::TokioExecutor,
+};
+
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix;
+#[cfg(all(unix, feature = "unix"))]
+pub use unix::UnixConnector;
+
+pub type HyperBody = Full<Bytes>;
+
+#[cfg(feature = "rustls-tls")]
+pub type DefaultConnector = hyper_rustls::HttpsConnector<HttpConnector>;
+#[cfg(not(feature = "rustls-tls"))]
+pub type DefaultConnector = HttpConnector;
+
+#[cfg(feature = "rustls-tls")]
+pub type BuildError = rustls::Error;
+#[cfg(not(feature = "rustls-tls"))]
+pub type BuildError = core::convert::Infallible;
+
+#[derive(Debug, Clone)]
+pub struct HyperClient<C = DefaultConnector> {
+    pub http_client: HyperHttpClient<C, HyperBody>,
+    pub timeout: Option<Duration>,
+    pub metrics: Option<Arc<dyn ClientMetrics>>,
+    pub sleeper: Arc<dyn Sleeper>,
+    pub max_response_body_size: Option<usize>,
+    /// Added to each request that doesn't have them already.
+    pub default_headers: HeaderMap,
+}
+
+impl Default for HyperClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperClient {
+    pub fn new() -> Self {
+        let mut http_connector = HttpConnector::new();
+        http_connector.set_connect_timeout(Some(Duration::from_secs(5)));
+
+        #[cfg(feature = "rustls-tls")]
+        let connector = {
+            http_connector.enforce_http(false);
+
+            let builder = hyper_rustls::HttpsConnectorBuilder::new()
+                .with_webpki_roots()
+                .https_or_http();
+            #[cfg(feature = "http1")]
+            let builder = builder.enable_http1();
+            #[cfg(feature = "http2")]
+            let builder = builder.enable_http2();
+            builder.wrap_connector(http_connector)
+        };
+        #[cfg(not(feature = "rustls-tls"))]
+        let connector = http_connector;
+
+        let mut client =
+            Self::with(HyperHttpClient::builder(TokioExecutor::new()).build(connector));
+        client.timeout = Some(Duration::from_secs(30));
+        client
+    }
+
+    pub fn from_config(config: &ClientConfig) -> Result<Self, FromConfigError<BuildError>> {
+        if config
+            .proxy
+            .as_ref()
+            .is_some_and(|x| x.http.is_some() || x.https.is_some())
+        {
+            return Err(FromConfigError::Unsupported("proxy"));
+        }
+
+        let mut http_connector = HttpConnector::new();
+        http_connector.set_connect_timeout(config.connect_timeout);
+
+        #[cfg(feature = "rustls-tls")]
+        let connector = {
+            http_connector.enforce_http(false);
+
+            let mut tls_config = http_api_client::tls::rustls_client_config(config)?;
+            // hyper-rustls sets ALPN from the enabled versions itself.
+            tls_config.alpn_protocols.clear();
+            let builder = hyper_rustls::HttpsConnectorBuilder::new()
+                .with_tls_config(tls_config)
+                .https_or_http();
+            match config.http_version {
+                #[cfg(feature = "http1")]
+                Some(VersionPreference::Http1Only) => {
+                    builder.enable_http1().wrap_connector(http_connector)
+                }
+                #[cfg(feature = "http2")]
+                Some(VersionPreference::Http2PriorKnowledge) => {
+                    builder.enable_http2().wrap_connector(http_connector)
+                }
+                #[allow(unreachable_patterns)]
+                Some(_) => return Err(FromConfigError::Unsupported("http_version")),
+                None => {
+                    #[cfg(feature = "http1")]
+                    let builder = builder.enable_http1();
+                    #[cfg(feature = "http2")]
+                    let builder = builder.enable_http2();
+                    builder.wrap_connector(http_connector)
+                }
+            }
+        };
+        #[cfg(not(feature = "rustls-tls"))]
+        let connector = {
+            if !config.root_certificates.is_empty() {
+                return Err(FromConfigError::Unsupported("root_certificates"));
+            }
+            if !config.built_in_root_certificates {
+                return Err(FromConfigError::Unsupported("built_in_root_certificates"));
+            }
+            if config.identity.is_some() {
+                return Err(FromConfigError::Unsupported("identity"));
+            }
+            if config.min_tls_version.is_some() {
+                return Err(FromConfigError::Unsupported("min_tls_version"));
+            }
+            if !config.pinned_public_keys.is_empty() {
+                return Err(FromConfigError::Unsupported("pinned_public_keys"));
+            }
+            http_connector
+        };
+
+        let mut builder = HyperHttpClient::builder(TokioExecutor::new());
+        builder.pool_idle_timeout(config.pool_idle_timeout);
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max);
+        }
+        if config.http_version == Some(VersionPreference::Http2PriorKnowledge) {
+            #[cfg(feature = "http2")]
+            builder.http2_only(true);
+            #[cfg(not(feature = "http2"))]
+            return Err(FromConfigError::Unsupported("http_version"));
+        }
+
+        let mut client = Self::with(builder.build(connector));
+        client.timeout = config.timeout;
+        client.max_response_body_size = config.max_response_body_size;
+        client.default_headers = config.header_map()?;
+        if let Some(user_agent) = &config.user_agent {
+            client.default_headers.insert(
+                USER_AGENT,
+                HeaderValue::from_str(user_agent)
+                    .map_err(|_| FromConfigError::InvalidHeader(USER_AGENT.to_string()))?,
+            );
+        }
+        Ok(client)
+    }
+}
+
+impl<C> HyperClient<C> {
+    pub fn with(http_client: HyperHttpClient<C, HyperBody>) -> Self {
+        Self {
+            http_client,
+            timeout: None,
+            metrics: None,
+            sleeper: Arc::new(TokioSleeper),
+            max_response_body_size: None,
+            default_headers: HeaderMap::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn ClientMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+}
+
+impl<C> HyperClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    async fn execute(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, HyperClientRespondError> {
+        let timeout = request
+            .extensions()
+            .get::<RequestTimeout>()
+            .map(|RequestTimeout(x)| *x)
+            .or(self.timeout);
+        let max_body_size = request
+            .extensions()
+            .get::<MaxResponseBodySize>()
+            .map(|MaxResponseBodySize(x)| *x)
+            .or(self.max_response_body_size);
+
+        // The connector is fixed when building the client, so these can't be honored per request.
+        let extensions = request.extensions();
+        for (present, name) in [
+            (extensions.get::<RequestProxy>().is_some(), "RequestProxy"),
+            (
+                extensions.get::<RedirectPolicy>().is_some(),
+                "RedirectPolicy",
+            ),
+            (
+                extensions.get::<VersionPreference>().is_some(),
+                "VersionPreference",
+            ),
+            (extensions.get::<TlsIdentity>().is_some(), "TlsIdentity"),
+        ] {
+            if present {
+                return Err(HyperClientRespondError::UnsupportedRequestOption(name));
+            }
+        }
+
+        // hyper uses the `http` types too, so only the body needs converting, without copying.
+        // hyper doesn't follow redirects, so the response is always from the request URI.
+        let effective_uri = EffectiveUri(request.uri().to_owned());
+
+        for (name, value) in &self.default_headers {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name, value.to_owned());
+            }
+        }
+
+        let fut = async {
+            let started_at = Instant::now();
+            let res = self
+                .http_client
+                .request(request.map(|body| Full::new(Bytes::from(body))))
+                .await
+                .map_err(HyperClientRespondError::RequestFailed)?;
+
+            let time_to_first_byte = started_at.elapsed();
+
+            let (mut head, body) = res.into_parts();
+            let body = match max_body_size {
+                Some(limit) => Limited::new(body, limit).collect().await.map_err(|err| {
+                    match err.downcast::<HyperError>() {
+                        Ok(err) => HyperClientRespondError::ReadBodyFailed(*err),
+                        Err(err) if err.is::<LengthLimitError>() => {
+                            HyperClientRespondError::BodyTooLarge { limit }
+                        }
+                        Err(err) => HyperClientRespondError::ReadLimitedBodyFailed(err),
+                    }
+                })?,
+                None => body
+                    .collect()
+                    .await
+                    .map_err(HyperClientRespondError::ReadBodyFailed)?,
+            }
+            .to_bytes();
+
+            if let Some(remote_addr) = head.extensions.get::<HttpInfo>().map(|x| x.remote_addr()) {
+                head.extensions.insert(RemoteAddr(remote_addr));
+            }
+            head.extensions.insert(effective_uri);
+            head.extensions.insert(ResponseTimings {
+                time_to_first_byte: Some(time_to_first_byte),
+                total: Some(started_at.elapsed()),
+                ..Default::default()
+            });
+
+            Ok(Response::from_parts(head, Vec::from(body)))
+        };
+
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| HyperClientRespondError::TimedOut)?,
+            None => fut.await,
+        }
+    }
+}
+
+#[async_trait]
+impl<C> Client for HyperClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    type RespondError = HyperClientRespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        #[cfg(feature = "tracing")]
+        let ret = http_api_client::instrumentation::instrument_respond(request, |request| {
+            self.execute(request)
+        })
+        .await;
+        #[cfg(not(feature = "tracing"))]
+        let ret = self.execute(request).await;
+
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+}
+
+impl<C> WithSleeper for HyperClient<C> {
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.sleeper.as_ref()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum HyperClientRespondError {
+    RequestFailed(HyperHttpClientError),
+    ReadBodyFailed(HyperError),
+    /// Reading the body through the size limit failed with neither a hyper error nor the limit.
+    ReadLimitedBodyFailed(Box<dyn std::error::Error + Send + Sync>),
+    BodyTooLarge {
+        limit: usize,
+    },
+    TimedOut,
+    UnsupportedRequestOption(&'static str),
+}
+impl core::fmt::Display for HyperClientRespondError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for HyperClientRespondError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(err) => Some(err),
+            Self::ReadBodyFailed(err) => Some(err),
+            Self::ReadLimitedBodyFailed(err) => Some(err.as_ref()),
+            Self::BodyTooLarge { .. } | Self::TimedOut | Self::UnsupportedRequestOption(_) => None,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{io::Error as IoError, path::PathBuf, sync::Arc};
+
+use http_api_client::http::Uri;
+use hyper_util::{client::legacy::Client as HyperHttpClient, rt::TokioIo};
+use tokio::net::UnixStream;
+use tower_service::Service;
+
+use crate::HyperClient;
+
+/// Connects every request to the same Unix domain socket, ignoring the scheme and authority of
+/// the request URI. Endpoints still need an absolute URI, e.g. `http://localhost/containers/json`.
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    path: Arc<PathBuf>,
+}
+
+impl UnixConnector {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Arc::new(path.into()),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Service<Uri> for UnixConnector {
+    type Response = TokioIo<UnixStream>;
+    type Error = IoError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move { UnixStream::connect(path.as_ref()).await.map(TokioIo::new) })
+    }
+}
+
+impl HyperClient<UnixConnector> {
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        let mut client = Self::with(
+            HyperHttpClient::builder(hyper_util::rt::TokioExecutor::new())
+                .build(UnixConnector::new(path)),
+        );
+        client.timeout = Some(Duration::from_secs(30));
+        client
+    }
+}
@@ -0,0 +1,291 @@
+use core::{convert::Infallible, time::Duration};
+use std::{io::Error as IoError, net::SocketAddr};
+
+use bytes::Bytes;
+use http_api_client_endpoint::{
+    http::Version, Body, EffectiveUri, Endpoint, RemoteAddr, Request, Response, ResponseTimings,
+};
+use http_api_hyper_client::{
+    http_api_client::{ClientConfig, ClientRespondEndpointError, VersionPreference},
+    Client as _, HyperClient, HyperClientRespondError,
+};
+use http_body_util::Full;
+use hyper::{body::Incoming, service::service_fn};
+use hyper_util::{
+    client::legacy::Client as HyperHttpClient,
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto::Builder as ServerBuilder,
+};
+use tokio::net::TcpListener;
+
+async fn serve() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let _ = ServerBuilder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .await;
+            });
+        }
+    });
+
+    addr
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.uri().path() == "/sleep" {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let body = format!(
+        "{} {} {:?} {}",
+        req.method(),
+        req.uri().path(),
+        req.version(),
+        req.headers()
+            .get("X-Foo")
+            .and_then(|x| x.to_str().ok())
+            .unwrap_or_default()
+    );
+
+    Ok(Response::builder()
+        .header("X-Bar", "Bar")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap())
+}
+
+#[derive(Clone)]
+struct EchoEndpoint {
+    addr: SocketAddr,
+    path: &'static str,
+    timeout: Option<Duration>,
+    max_response_body_size: Option<usize>,
+}
+impl Endpoint for EchoEndpoint {
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = (Version, Option<String>, String);
+    type ParseResponseError = IoError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        Ok(Request::builder()
+            .uri(format!("http://{}{}", self.addr, self.path))
+            .header("X-Foo", "Foo")
+            .body(vec![])
+            .unwrap())
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        Ok((
+            response.version(),
+            response
+                .headers()
+                .get("X-Bar")
+                .and_then(|x| x.to_str().ok())
+                .map(ToOwned::to_owned),
+            String::from_utf8(response.into_body()).unwrap(),
+        ))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        self.max_response_body_size
+    }
+}
+
+#[tokio::test]
+async fn test_http1() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::new();
+    let (version, x_bar, body) = client.respond_endpoint(&endpoint).await.unwrap();
+    assert_eq!(version, Version::HTTP_11);
+    assert_eq!(x_bar.as_deref(), Some("Bar"));
+    assert_eq!(body, "GET /echo HTTP/1.1 Foo");
+}
+
+#[tokio::test]
+async fn test_http2_prior_knowledge() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::with(
+        HyperHttpClient::builder(TokioExecutor::new())
+            .http2_only(true)
+            .build_http(),
+    );
+    let (version, _, body) = client.respond_endpoint(&endpoint).await.unwrap();
+    assert_eq!(version, Version::HTTP_2);
+    assert_eq!(body, "GET /echo HTTP/2.0 Foo");
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/sleep",
+        timeout: Some(Duration::from_millis(100)),
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::new();
+    match client.respond_endpoint(&endpoint).await {
+        Err(ClientRespondEndpointError::RespondFailed(HyperClientRespondError::TimedOut)) => {}
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[tokio::test]
+async fn test_max_response_body_size() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: Some(4),
+    };
+
+    let client = HyperClient::new();
+    match client.respond_endpoint(&endpoint).await {
+        Err(ClientRespondEndpointError::RespondFailed(HyperClientRespondError::BodyTooLarge {
+            limit: 4,
+        })) => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    let client = HyperClient::new().with_max_response_body_size(4);
+    let endpoint = EchoEndpoint {
+        max_response_body_size: Some(1024),
+        ..endpoint
+    };
+    let (_, _, body) = client.respond_endpoint(&endpoint).await.unwrap();
+    assert_eq!(body, "GET /echo HTTP/1.1 Foo");
+}
+
+#[tokio::test]
+async fn test_response_metadata() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::new();
+    let mut extensions = None;
+    client
+        .respond_endpoint_with_callback(
+            &endpoint,
+            |req| req,
+            |res| extensions = Some(res.extensions().to_owned()),
+        )
+        .await
+        .unwrap();
+    let extensions = extensions.unwrap();
+    assert_eq!(extensions.get::<RemoteAddr>(), Some(&RemoteAddr(addr)));
+    assert_eq!(
+        extensions.get::<EffectiveUri>().unwrap().0,
+        format!("http://{}/echo", addr).as_str()
+    );
+    assert!(extensions.get::<ResponseTimings>().unwrap().total.is_some());
+}
+
+#[tokio::test]
+async fn test_from_config() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::from_config(
+        &ClientConfig::new()
+            .with_http_version(VersionPreference::Http2PriorKnowledge)
+            .with_default_header("X-Foo", "Ignored"),
+    )
+    .unwrap();
+    let (version, _, body) = client.respond_endpoint(&endpoint).await.unwrap();
+    assert_eq!(version, Version::HTTP_2);
+    assert_eq!(body, "GET /echo HTTP/2.0 Foo");
+
+    let client =
+        HyperClient::from_config(&ClientConfig::new().with_max_response_body_size(4)).unwrap();
+    match client.respond_endpoint(&endpoint).await {
+        Err(ClientRespondEndpointError::RespondFailed(HyperClientRespondError::BodyTooLarge {
+            limit: 4,
+        })) => {}
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[tokio::test]
+async fn test_unsupported_request_option() {
+    let addr = serve().await;
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = HyperClient::new();
+    match client
+        .respond_endpoint_with_callback(
+            &endpoint,
+            |mut req| {
+                req.extensions_mut().insert(VersionPreference::Http1Only);
+                req
+            },
+            |_| {},
+        )
+        .await
+    {
+        Err(ClientRespondEndpointError::RespondFailed(
+            HyperClientRespondError::UnsupportedRequestOption("VersionPreference"),
+        )) => {}
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[cfg(not(feature = "rustls-tls"))]
+#[test]
+fn test_from_config_without_tls() {
+    use http_api_hyper_client::http_api_client::config::{FromConfigError, TlsVersion};
+
+    for config in [
+        ClientConfig::new().with_min_tls_version(TlsVersion::Tls1_3),
+        ClientConfig::new().without_built_in_root_certificates(),
+    ] {
+        assert!(matches!(
+            HyperClient::from_config(&config),
+            Err(FromConfigError::Unsupported(
+                "min_tls_version" | "built_in_root_certificates"
+            ))
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(all(unix, feature = "unix"))]
+
+use core::convert::Infallible;
+use std::{io::Error as IoError, path::PathBuf};
+
+use bytes::Bytes;
+use http_api_client_endpoint::{Body, Endpoint, Request, Response};
+use http_api_hyper_client::{Client as _, HyperClient};
+use http_body_util::Full;
+use hyper::{body::Incoming, service::service_fn};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto::Builder as ServerBuilder,
+};
+use tokio::net::UnixListener;
+
+async fn serve(path: PathBuf) {
+    let listener = UnixListener::bind(path).unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let _ = ServerBuilder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .await;
+            });
+        }
+    });
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let body = format!(
+        "{} {}",
+        req.method(),
+        req.uri()
+            .path_and_query()
+            .map(|x| x.as_str())
+            .unwrap_or_default()
+    );
+
+    Ok(Response::new(Full::new(Bytes::from(body))))
+}
+
+#[derive(Clone)]
+struct ContainersEndpoint;
+impl Endpoint for ContainersEndpoint {
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = String;
+    type ParseResponseError = IoError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        Ok(Request::builder()
+            .uri("http://localhost/containers/json?all=true")
+            .body(vec![])
+            .unwrap())
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        Ok(String::from_utf8(response.into_body()).unwrap())
+    }
+}
+
+#[tokio::test]
+async fn test_unix() {
+    let path = std::env::temp_dir().join(format!(
+        "http-api-hyper-client-test-{}.sock",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    serve(path.clone()).await;
+
+    let client = HyperClient::unix(&path);
+    assert_eq!(
+        client.respond_endpoint(&ContainersEndpoint).await.unwrap(),
+        "GET /containers/json?all=true"
+    );
+
+    let _ = std::fs::remove_file(&path);
+}
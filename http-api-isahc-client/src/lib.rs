@@ -2,24 +2,37 @@ pub use http_api_client;
 pub use isahc;
 
 use core::time::Duration;
+use std::sync::Arc;
 
-pub use http_api_client::Client;
-#[cfg(any(
-    feature = "with-sleep-via-tokio",
-    feature = "with-sleep-via-async-timer",
-    feature = "with-sleep-via-async-io"
-))]
-pub use http_api_client::RetryableClient;
-use http_api_client::{async_trait, Body, Request, Response};
+use futures_lite::AsyncReadExt as _;
+use http_api_client::{
+    async_trait,
+    config::{FromConfigError, IdentityConfig},
+    http::{HeaderName, HeaderValue, StatusCode, Version},
+    sleep::AsyncIoSleeper,
+    Body, ClientConfig, ClientMetrics, EffectiveUri, MaxResponseBodySize, ProxyConfig,
+    RedirectPolicy, RemoteAddr, Request, RequestProxy, RequestTimeout, Response, ResponseTimings,
+    Sleeper, TlsIdentity, VersionPreference, WithSleeper,
+};
+pub use http_api_client::{Client, RetryableClient};
 use isahc::{
-    config::Configurable as _, AsyncReadResponseExt as _, Error as IsahcError,
-    HttpClient as IsahcHttpClient,
+    auth::Credentials,
+    config::{
+        CaCertificate, ClientCertificate, Configurable as _, PrivateKey,
+        RedirectPolicy as IsahcRedirectPolicy, VersionNegotiation,
+    },
+    http as isahc_http, Error as IsahcError, HttpClient as IsahcHttpClient, ResponseExt as _,
 };
 
 #[derive(Debug, Clone)]
 pub struct IsahcClient {
     pub http_client: IsahcHttpClient,
     pub body_buf_default_capacity: usize,
+    pub metrics: Option<Arc<dyn ClientMetrics>>,
+    pub sleeper: Arc<dyn Sleeper>,
+    pub max_response_body_size: Option<usize>,
+    /// Applied to each request, as curl only has a single proxy for all schemes.
+    pub proxy: Option<ProxyConfig>,
 }
 
 impl IsahcClient {
@@ -28,72 +41,361 @@ impl IsahcClient {
             IsahcHttpClient::builder()
                 .connect_timeout(Duration::from_secs(5))
                 .timeout(Duration::from_secs(30))
+                .metrics(true)
                 .build()?,
         ))
     }
 
+    pub fn from_config(config: &ClientConfig) -> Result<Self, FromConfigError<IsahcError>> {
+        if config.pool_max_idle_per_host.is_some() {
+            return Err(FromConfigError::Unsupported("pool_max_idle_per_host"));
+        }
+        if config.min_tls_version.is_some() {
+            return Err(FromConfigError::Unsupported("min_tls_version"));
+        }
+        if !config.pinned_public_keys.is_empty() {
+            return Err(FromConfigError::Unsupported("pinned_public_keys"));
+        }
+        // isahc is built without its `http2` feature, which would pull in libnghttp2.
+        if config.http_version == Some(VersionPreference::Http2PriorKnowledge) {
+            return Err(FromConfigError::Unsupported("http_version"));
+        }
+
+        let mut builder = IsahcHttpClient::builder().metrics(true);
+        // curl's CA bundle replaces the system roots instead of adding to them.
+        match (
+            config.built_in_root_certificates,
+            config.root_certificates.as_slice(),
+        ) {
+            (true, []) => {}
+            (false, [path]) => builder = builder.ssl_ca_certificate(CaCertificate::file(path)),
+            (true, _) => return Err(FromConfigError::Unsupported("root_certificates")),
+            (false, _) => return Err(FromConfigError::Unsupported("built_in_root_certificates")),
+        }
+        match &config.identity {
+            Some(IdentityConfig::Pem { cert, key }) => {
+                builder = builder.ssl_client_certificate(ClientCertificate::pem_file(
+                    cert,
+                    PrivateKey::pem_file(key, None),
+                ))
+            }
+            Some(IdentityConfig::Pkcs12 { path, password }) => {
+                builder = builder.ssl_client_certificate(ClientCertificate::pkcs12_file(
+                    path,
+                    password.to_owned(),
+                ))
+            }
+            None => {}
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.default_header("user-agent", user_agent.as_str());
+        }
+        for (name, value) in config.header_map::<IsahcError>()?.iter() {
+            builder = builder.default_header(name.as_str(), value.as_bytes());
+        }
+        if config.http_version == Some(VersionPreference::Http1Only) {
+            builder = builder.version_negotiation(VersionNegotiation::http11());
+        }
+        if let Some(ttl) = config.pool_idle_timeout {
+            builder = builder.connection_cache_ttl(ttl);
+        }
+
+        let mut client = Self::with(builder.build().map_err(FromConfigError::BuildFailed)?);
+        client.max_response_body_size = config.max_response_body_size;
+
+        if let Some(proxy_config) = &config.proxy {
+            for proxy in [&proxy_config.http, &proxy_config.https]
+                .into_iter()
+                .flatten()
+            {
+                parse_uri(&proxy.url).map_err(FromConfigError::BuildFailed)?;
+            }
+            client.proxy = Some(proxy_config.to_owned());
+        }
+
+        Ok(client)
+    }
+
     pub fn with(http_client: IsahcHttpClient) -> Self {
         Self {
             http_client,
             body_buf_default_capacity: 4 * 1024,
+            metrics: None,
+            sleeper: Arc::new(AsyncIoSleeper),
+            max_response_body_size: None,
+            proxy: None,
         }
     }
-}
 
-#[async_trait]
-impl Client for IsahcClient {
-    type RespondError = IsahcError;
+    pub fn with_metrics(mut self, metrics: Arc<dyn ClientMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+
+    async fn execute(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, IsahcClientRespondError> {
+        let max_body_size = request
+            .extensions()
+            .get::<MaxResponseBodySize>()
+            .map(|MaxResponseBodySize(x)| *x)
+            .or(self.max_response_body_size);
+
+        // curl has no notion of origins, so it can't stop at the first cross-origin redirect.
+        if let Some(RedirectPolicy::SameOrigin(_)) = request.extensions().get::<RedirectPolicy>() {
+            return Err(IsahcClientRespondError::UnsupportedRequestOption(
+                "RedirectPolicy::SameOrigin",
+            ));
+        }
+        if let Some(VersionPreference::Http2PriorKnowledge) =
+            request.extensions().get::<VersionPreference>()
+        {
+            return Err(IsahcClientRespondError::UnsupportedRequestOption(
+                "VersionPreference::Http2PriorKnowledge",
+            ));
+        }
+
+        let request = to_isahc_request(request, self.proxy.as_ref())?;
 
-    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
         let res = self.http_client.send_async(request).await?;
-        let (head, body) = res.into_parts();
+        let (head, mut body) = res.into_parts();
 
-        let mut body_buf = Vec::with_capacity(
-            body.len().unwrap_or(self.body_buf_default_capacity as u64) as usize,
-        );
+        let content_length = body.len();
+        if let (Some(limit), Some(content_length)) = (max_body_size, content_length) {
+            if content_length > limit as u64 {
+                return Err(IsahcClientRespondError::BodyTooLarge { limit });
+            }
+        }
+        // `Content-Length` comes from the server, so it only ever shrinks the allocation.
+        let capacity = content_length.map_or(self.body_buf_default_capacity, |x| {
+            x.min(self.body_buf_default_capacity as u64) as usize
+        });
+        let mut body_buf = Vec::with_capacity(capacity);
+
+        match max_body_size {
+            Some(limit) => {
+                (&mut body)
+                    .take(limit as u64 + 1)
+                    .read_to_end(&mut body_buf)
+                    .await
+                    .map_err(IsahcError::from)?;
+                if body_buf.len() > limit {
+                    return Err(IsahcClientRespondError::BodyTooLarge { limit });
+                }
+            }
+            None => {
+                body.read_to_end(&mut body_buf)
+                    .await
+                    .map_err(IsahcError::from)?;
+            }
+        }
 
-        let mut res = Response::from_parts(head, body);
-        res.copy_to(&mut body_buf).await?;
+        let res = isahc_http::Response::from_parts(head, ());
+        let remote_addr = res.remote_addr();
+        let effective_uri = res.effective_uri().and_then(|x| x.to_string().parse().ok());
+        // Only there when the client was built with `metrics(true)`.
+        let timings = res.metrics().map(|metrics| ResponseTimings {
+            dns_lookup: Some(metrics.name_lookup_time()),
+            connect: Some(metrics.connect_time()),
+            tls_handshake: Some(metrics.secure_connect_time()),
+            time_to_first_byte: Some(metrics.transfer_start_time()),
+            total: Some(metrics.total_time()),
+        });
 
-        let (head, _) = res.into_parts();
-        let res = Response::from_parts(head, body_buf);
+        let mut res = from_isahc_response(res, body_buf)?;
+        if let Some(remote_addr) = remote_addr {
+            res.extensions_mut().insert(RemoteAddr(remote_addr));
+        }
+        if let Some(effective_uri) = effective_uri {
+            res.extensions_mut().insert(EffectiveUri(effective_uri));
+        }
+        if let Some(timings) = timings {
+            res.extensions_mut().insert(timings);
+        }
 
         Ok(res)
     }
 }
 
-#[cfg(all(
-    feature = "with-sleep-via-tokio",
-    not(feature = "with-sleep-via-async-timer"),
-    not(feature = "with-sleep-via-async-io")
-))]
-#[async_trait]
-impl RetryableClient for IsahcClient {
-    async fn sleep(&self, dur: Duration) {
-        async_sleep::sleep::<async_sleep::impl_tokio::Sleep>(dur).await;
+// isahc 1 is built on `http` 0.2, so the request and response heads are copied over.
+fn to_isahc_request(
+    request: Request<Body>,
+    proxy_config: Option<&ProxyConfig>,
+) -> Result<isahc_http::Request<Body>, IsahcError> {
+    let (head, body) = request.into_parts();
+
+    let mut builder = isahc_http::Request::builder()
+        .method(head.method.as_str())
+        .uri(head.uri.to_string())
+        .version(match head.version {
+            Version::HTTP_09 => isahc_http::Version::HTTP_09,
+            Version::HTTP_10 => isahc_http::Version::HTTP_10,
+            Version::HTTP_2 => isahc_http::Version::HTTP_2,
+            Version::HTTP_3 => isahc_http::Version::HTTP_3,
+            _ => isahc_http::Version::HTTP_11,
+        });
+    for (name, value) in head.headers.iter() {
+        builder = builder.header(name.as_str(), value.as_bytes());
+    }
+    // isahc only reads per-request config that was set through `Configurable`.
+    if let Some(RequestTimeout(timeout)) = head.extensions.get::<RequestTimeout>() {
+        builder = builder.timeout(*timeout);
+    }
+    match head.extensions.get::<RequestProxy>() {
+        Some(RequestProxy::Disabled) => builder = builder.proxy(None),
+        Some(RequestProxy::Url(uri)) => builder = builder.proxy(Some(parse_uri(&uri.to_string())?)),
+        None => {
+            if let Some(proxy_config) = proxy_config {
+                match proxy_config.for_scheme(head.uri.scheme_str().unwrap_or_default()) {
+                    Some(proxy) => {
+                        builder = builder
+                            .proxy(Some(parse_uri(&proxy.url)?))
+                            .proxy_blacklist(proxy_config.no_proxy.iter().cloned());
+                        if let Some(auth) = &proxy.auth {
+                            builder = builder.proxy_credentials(Credentials::new(
+                                auth.username.as_str(),
+                                auth.password.as_str(),
+                            ));
+                        }
+                    }
+                    None => builder = builder.proxy(None),
+                }
+            }
+        }
+    }
+    match head.extensions.get::<RedirectPolicy>() {
+        Some(RedirectPolicy::None) => builder = builder.redirect_policy(IsahcRedirectPolicy::None),
+        Some(RedirectPolicy::Limit(max)) => {
+            builder = builder.redirect_policy(IsahcRedirectPolicy::Limit(
+                (*max).try_into().unwrap_or(u32::MAX),
+            ))
+        }
+        Some(RedirectPolicy::SameOrigin(_)) | None => {}
+    }
+    if let Some(VersionPreference::Http1Only) = head.extensions.get::<VersionPreference>() {
+        builder = builder.version_negotiation(VersionNegotiation::http11());
     }
+    match head.extensions.get::<TlsIdentity>() {
+        Some(TlsIdentity::Pem { cert, key }) => {
+            builder = builder.ssl_client_certificate(ClientCertificate::pem(
+                cert.to_owned(),
+                PrivateKey::pem(key.to_owned(), None),
+            ))
+        }
+        Some(TlsIdentity::Pkcs12 { der, password }) => {
+            builder = builder.ssl_client_certificate(ClientCertificate::pkcs12(
+                der.to_owned(),
+                password.to_owned(),
+            ))
+        }
+        None => {}
+    }
+
+    Ok(builder.body(body)?)
 }
 
-#[cfg(all(
-    not(feature = "with-sleep-via-tokio"),
-    feature = "with-sleep-via-async-timer",
-    not(feature = "with-sleep-via-async-io")
-))]
-#[async_trait]
-impl RetryableClient for IsahcClient {
-    async fn sleep(&self, dur: Duration) {
-        async_sleep::sleep::<async_sleep::impl_async_timer::AsyncTimerPlatform>(dur).await;
+fn parse_uri(uri: &str) -> Result<isahc_http::Uri, IsahcError> {
+    Ok(uri
+        .parse::<isahc_http::Uri>()
+        .map_err(isahc_http::Error::from)?)
+}
+
+fn from_isahc_response<T>(
+    response: isahc_http::Response<T>,
+    body: Body,
+) -> Result<Response<Body>, IsahcError> {
+    let (head, _) = response.into_parts();
+
+    let mut res = Response::new(body);
+    *res.status_mut() = StatusCode::from_u16(head.status.as_u16())
+        .map_err(|err| IsahcError::from(std::io::Error::other(err)))?;
+    *res.version_mut() = match head.version {
+        isahc_http::Version::HTTP_09 => Version::HTTP_09,
+        isahc_http::Version::HTTP_10 => Version::HTTP_10,
+        isahc_http::Version::HTTP_2 => Version::HTTP_2,
+        isahc_http::Version::HTTP_3 => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    };
+    let headers = res.headers_mut();
+    headers.reserve(head.headers.len());
+    for (name, value) in head.headers.iter() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            headers.append(name, value);
+        }
     }
+
+    Ok(res)
 }
 
-#[cfg(all(
-    not(feature = "with-sleep-via-tokio"),
-    not(feature = "with-sleep-via-async-timer"),
-    feature = "with-sleep-via-async-io"
-))]
 #[async_trait]
-impl RetryableClient for IsahcClient {
-    async fn sleep(&self, dur: Duration) {
-        async_sleep::sleep::<async_sleep::impl_async_io::Timer>(dur).await;
+impl Client for IsahcClient {
+    type RespondError = IsahcClientRespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        #[cfg(feature = "tracing")]
+        let ret = http_api_client::instrumentation::instrument_respond(request, |request| {
+            self.execute(request)
+        })
+        .await;
+        #[cfg(not(feature = "tracing"))]
+        let ret = self.execute(request).await;
+
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+}
+
+impl WithSleeper for IsahcClient {
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.sleeper.as_ref()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum IsahcClientRespondError {
+    RequestFailed(IsahcError),
+    BodyTooLarge { limit: usize },
+    UnsupportedRequestOption(&'static str),
+}
+impl core::fmt::Display for IsahcClientRespondError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for IsahcClientRespondError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(err) => Some(err),
+            Self::BodyTooLarge { .. } | Self::UnsupportedRequestOption(_) => None,
+        }
+    }
+}
+impl From<IsahcError> for IsahcClientRespondError {
+    fn from(err: IsahcError) -> Self {
+        Self::RequestFailed(err)
     }
 }
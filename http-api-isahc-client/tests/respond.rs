@@ -0,0 +1,374 @@
+use core::time::Duration;
+use std::{
+    io::{BufRead as _, BufReader, Error as IoError, Write as _},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use futures_executor::block_on;
+use http_api_client_endpoint::{
+    http::StatusCode, Body, EffectiveUri, Endpoint, RedirectPolicy, RemoteAddr, Request, Response,
+    ResponseTimings, RetryableEndpoint, RetryableEndpointRetry, VersionPreference,
+};
+use http_api_isahc_client::{
+    http_api_client::{
+        config::{FromConfigError, Proxy},
+        ClientConfig, ClientRespondEndpointError, ProxyConfig,
+    },
+    isahc::error::ErrorKind,
+    Client as _, IsahcClient, IsahcClientRespondError, RetryableClient as _,
+};
+
+// Answers `/flaky` with 503 until it was requested twice, `/sleep` after 1s,
+// `/redirect` with a redirect to `/echo`, anything else with 200.
+fn serve() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let flaky_count = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let flaky_count = flaky_count.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut x_foo = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("x-foo:") {
+                        x_foo = value.trim().to_owned();
+                    }
+                }
+
+                let path = request_line.split(' ').nth(1).unwrap_or_default();
+                let status = match path {
+                    "/flaky" if flaky_count.fetch_add(1, Ordering::SeqCst) < 2 => {
+                        "503 Service Unavailable"
+                    }
+                    "/sleep" => {
+                        thread::sleep(Duration::from_secs(1));
+                        "200 OK"
+                    }
+                    "/redirect" => "302 Found\r\nLocation: /echo",
+                    _ => "200 OK",
+                };
+                let body = format!("{} {}", path, x_foo);
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            });
+        }
+    });
+
+    addr
+}
+
+#[derive(Clone)]
+struct EchoEndpoint {
+    addr: SocketAddr,
+    path: &'static str,
+    timeout: Option<Duration>,
+    max_response_body_size: Option<usize>,
+}
+impl Endpoint for EchoEndpoint {
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = String;
+    type ParseResponseError = IoError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        Ok(Request::builder()
+            .uri(format!("http://{}{}", self.addr, self.path))
+            .header("X-Foo", "foo")
+            .body(vec![])
+            .unwrap())
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        Ok(String::from_utf8(response.into_body()).unwrap())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        self.max_response_body_size
+    }
+}
+
+impl RetryableEndpoint for EchoEndpoint {
+    type RetryReason = StatusCode;
+
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = String;
+    type ParseResponseError = IoError;
+
+    fn render_request(
+        &self,
+        _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+    ) -> Result<Request<Body>, <Self as RetryableEndpoint>::RenderRequestError> {
+        Endpoint::render_request(self)
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+        _retry: Option<&RetryableEndpointRetry<Self::RetryReason>>,
+    ) -> Result<
+        Result<<Self as RetryableEndpoint>::ParseResponseOutput, Self::RetryReason>,
+        <Self as RetryableEndpoint>::ParseResponseError,
+    > {
+        if response.status().is_success() {
+            Endpoint::parse_response(self, response).map(Ok)
+        } else {
+            Ok(Err(response.status()))
+        }
+    }
+
+    fn next_retry_in(&self, _retry: &RetryableEndpointRetry<Self::RetryReason>) -> Duration {
+        Duration::from_millis(10)
+    }
+}
+
+#[test]
+fn test_respond_endpoint() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = IsahcClient::new().unwrap();
+    assert_eq!(
+        block_on(client.respond_endpoint(&endpoint)).unwrap(),
+        "/echo foo".to_owned()
+    );
+}
+
+#[test]
+fn test_respond_endpoint_until_done() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/flaky",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = IsahcClient::new().unwrap();
+    let mut retries = vec![];
+    assert_eq!(
+        block_on(client.respond_endpoint_until_done_with_callback(
+            &endpoint,
+            |req, _| req,
+            |_, retry| retries.push(retry.map(|x| x.count))
+        ))
+        .unwrap(),
+        "/flaky foo".to_owned()
+    );
+    assert_eq!(retries, vec![None, Some(1), Some(2)]);
+}
+
+#[test]
+fn test_timeout() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/sleep",
+        timeout: Some(Duration::from_millis(100)),
+        max_response_body_size: None,
+    };
+
+    let client = IsahcClient::new().unwrap();
+    match block_on(client.respond_endpoint(&endpoint)) {
+        Err(ClientRespondEndpointError::RespondFailed(IsahcClientRespondError::RequestFailed(
+            err,
+        ))) if *err.kind() == ErrorKind::Timeout => {}
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[test]
+fn test_max_response_body_size() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: Some(4),
+    };
+
+    let client = IsahcClient::new().unwrap();
+    match block_on(client.respond_endpoint(&endpoint)) {
+        Err(ClientRespondEndpointError::RespondFailed(IsahcClientRespondError::BodyTooLarge {
+            limit: 4,
+        })) => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    let client = IsahcClient::new().unwrap().with_max_response_body_size(4);
+    let endpoint = EchoEndpoint {
+        max_response_body_size: Some(1024),
+        ..endpoint
+    };
+    assert_eq!(
+        block_on(client.respond_endpoint(&endpoint)).unwrap(),
+        "/echo foo".to_owned()
+    );
+}
+
+#[test]
+fn test_response_metadata() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = IsahcClient::new().unwrap();
+    let mut extensions = None;
+    block_on(client.respond_endpoint_with_callback(
+        &endpoint,
+        |req| req,
+        |res| extensions = Some(res.extensions().to_owned()),
+    ))
+    .unwrap();
+    let extensions = extensions.unwrap();
+    assert_eq!(extensions.get::<RemoteAddr>(), Some(&RemoteAddr(addr)));
+    assert_eq!(
+        extensions.get::<EffectiveUri>().unwrap().0,
+        format!("http://{}/echo", addr).as_str()
+    );
+    assert!(extensions.get::<ResponseTimings>().unwrap().total.is_some());
+}
+
+#[test]
+fn test_redirect_policy() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/redirect",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client = IsahcClient::new().unwrap();
+    assert_eq!(
+        block_on(client.respond_endpoint(&endpoint)).unwrap(),
+        "/redirect foo"
+    );
+    assert_eq!(
+        block_on(client.respond_endpoint_with_callback(
+            &endpoint,
+            |mut req| {
+                req.extensions_mut().insert(RedirectPolicy::Limit(1));
+                req
+            },
+            |_| {},
+        ))
+        .unwrap(),
+        "/echo foo"
+    );
+}
+
+#[test]
+fn test_proxy() {
+    let addr = serve();
+    let unreachable_addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let client = IsahcClient::from_config(
+        &ClientConfig::new().with_proxy(ProxyConfig::all(Proxy::new(format!("http://{}", addr)))),
+    )
+    .unwrap();
+    let endpoint = EchoEndpoint {
+        addr: unreachable_addr,
+        path: "/echo",
+        timeout: None,
+        max_response_body_size: None,
+    };
+    assert_eq!(
+        block_on(client.respond_endpoint(&endpoint)).unwrap(),
+        format!("http://{}/echo foo", unreachable_addr)
+    );
+
+    let client = IsahcClient::from_config(
+        &ClientConfig::new().with_proxy(
+            ProxyConfig::all(Proxy::new(format!("http://{}", unreachable_addr)))
+                .with_no_proxy("127.0.0.1"),
+        ),
+    )
+    .unwrap();
+    let endpoint = EchoEndpoint { addr, ..endpoint };
+    assert_eq!(
+        block_on(client.respond_endpoint(&endpoint)).unwrap(),
+        "/echo foo"
+    );
+}
+
+#[test]
+fn test_from_config() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/sleep",
+        timeout: None,
+        max_response_body_size: None,
+    };
+
+    let client =
+        IsahcClient::from_config(&ClientConfig::new().with_timeout(Duration::from_millis(100)))
+            .unwrap();
+    match block_on(client.respond_endpoint(&endpoint)) {
+        Err(ClientRespondEndpointError::RespondFailed(IsahcClientRespondError::RequestFailed(
+            err,
+        ))) if *err.kind() == ErrorKind::Timeout => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    let client =
+        IsahcClient::from_config(&ClientConfig::new().with_max_response_body_size(4)).unwrap();
+    let endpoint = EchoEndpoint {
+        path: "/echo",
+        ..endpoint
+    };
+    match block_on(client.respond_endpoint(&endpoint)) {
+        Err(ClientRespondEndpointError::RespondFailed(IsahcClientRespondError::BodyTooLarge {
+            limit: 4,
+        })) => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    assert!(matches!(
+        IsahcClient::from_config(
+            &ClientConfig::new().with_http_version(VersionPreference::Http2PriorKnowledge)
+        ),
+        Err(FromConfigError::Unsupported("http_version"))
+    ));
+}
@@ -2,14 +2,28 @@ pub use http_api_client;
 pub use reqwest;
 
 use core::time::Duration;
+use std::{sync::Arc, time::Instant};
 
-use http_api_client::{async_trait, Body, Request, Response};
+use http_api_client::{
+    async_trait,
+    config::{FromConfigError, Proxy},
+    sleep::TokioSleeper,
+    tls, Body, ClientConfig, ClientMetrics, EffectiveUri, MaxResponseBodySize, RedirectPolicy,
+    RemoteAddr, Request, RequestProxy, RequestTimeout, Response, ResponseTimings, Sleeper,
+    TlsIdentity, VersionPreference, WithSleeper,
+};
 pub use http_api_client::{Client, RetryableClient};
-use reqwest::{Client as ReqwestHttpClient, Error as ReqwestError, Request as ReqwestRequest};
+use reqwest::{
+    redirect::Policy as ReqwestRedirectPolicy, Client as ReqwestHttpClient, Error as ReqwestError,
+    NoProxy, Proxy as ReqwestProxy, Request as ReqwestRequest,
+};
 
 #[derive(Debug, Clone)]
 pub struct ReqwestClient {
     pub http_client: ReqwestHttpClient,
+    pub metrics: Option<Arc<dyn ClientMetrics>>,
+    pub sleeper: Arc<dyn Sleeper>,
+    pub max_response_body_size: Option<usize>,
 }
 
 impl ReqwestClient {
@@ -22,28 +36,171 @@ impl ReqwestClient {
         ))
     }
 
+    /// Like [`new`](Self::new), but doesn't follow redirects, for use under a
+    /// [`RedirectClient`](http_api_client::RedirectClient) so the [`RedirectPolicy`] of each
+    /// endpoint applies.
+    pub fn without_redirects() -> Result<Self, ReqwestError> {
+        Ok(Self::with(
+            ReqwestHttpClient::builder()
+                .redirect(ReqwestRedirectPolicy::none())
+                .connect_timeout(Duration::from_secs(5))
+                .timeout(Duration::from_secs(30))
+                .build()?,
+        ))
+    }
+
+    pub fn from_config(config: &ClientConfig) -> Result<Self, FromConfigError<ReqwestError>> {
+        let mut builder = ReqwestHttpClient::builder()
+            .default_headers(config.header_map()?)
+            .pool_idle_timeout(config.pool_idle_timeout);
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        builder = builder.use_preconfigured_tls(tls::rustls_client_config(config)?);
+        match config.http_version {
+            Some(VersionPreference::Http1Only) => builder = builder.http1_only(),
+            Some(VersionPreference::Http2PriorKnowledge) => {
+                builder = builder.http2_prior_knowledge()
+            }
+            None => {}
+        }
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+
+        if let Some(proxy_config) = &config.proxy {
+            let no_proxy = NoProxy::from_string(&proxy_config.no_proxy.join(","));
+            let to_reqwest_proxy = |proxy: &Proxy, reqwest_proxy: ReqwestProxy| {
+                let reqwest_proxy = match &proxy.auth {
+                    Some(auth) => reqwest_proxy.basic_auth(&auth.username, &auth.password),
+                    None => reqwest_proxy,
+                };
+                reqwest_proxy.no_proxy(no_proxy.clone())
+            };
+
+            builder = builder.no_proxy();
+            if let Some(proxy) = &proxy_config.http {
+                let reqwest_proxy =
+                    ReqwestProxy::http(&proxy.url).map_err(FromConfigError::BuildFailed)?;
+                builder = builder.proxy(to_reqwest_proxy(proxy, reqwest_proxy));
+            }
+            if let Some(proxy) = &proxy_config.https {
+                let reqwest_proxy =
+                    ReqwestProxy::https(&proxy.url).map_err(FromConfigError::BuildFailed)?;
+                builder = builder.proxy(to_reqwest_proxy(proxy, reqwest_proxy));
+            }
+        }
+
+        let mut client = Self::with(builder.build().map_err(FromConfigError::BuildFailed)?);
+        client.max_response_body_size = config.max_response_body_size;
+        Ok(client)
+    }
+
     pub fn with(http_client: ReqwestHttpClient) -> Self {
-        Self { http_client }
+        Self {
+            http_client,
+            metrics: None,
+            sleeper: Arc::new(TokioSleeper),
+            max_response_body_size: None,
+        }
     }
-}
 
-#[async_trait]
-impl Client for ReqwestClient {
-    type RespondError = ReqwestError;
+    pub fn with_metrics(mut self, metrics: Arc<dyn ClientMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 
-    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
-        let res_reqwest = self
-            .http_client
-            .execute(ReqwestRequest::try_from(request)?)
-            .await?;
+    pub fn with_sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+
+    async fn execute(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, ReqwestClientRespondError> {
+        let timeout = request.extensions().get::<RequestTimeout>().copied();
+        let max_body_size = request
+            .extensions()
+            .get::<MaxResponseBodySize>()
+            .map(|MaxResponseBodySize(x)| *x)
+            .or(self.max_response_body_size);
+
+        // reqwest only takes these when building the client, so they can't be honored per request.
+        let extensions = request.extensions();
+        for (present, name) in [
+            (extensions.get::<RequestProxy>().is_some(), "RequestProxy"),
+            (
+                extensions.get::<RedirectPolicy>().is_some(),
+                "RedirectPolicy",
+            ),
+            (
+                extensions.get::<VersionPreference>().is_some(),
+                "VersionPreference",
+            ),
+            (extensions.get::<TlsIdentity>().is_some(), "TlsIdentity"),
+        ] {
+            if present {
+                return Err(ReqwestClientRespondError::UnsupportedRequestOption(name));
+            }
+        }
+
+        let mut req_reqwest = ReqwestRequest::try_from(request)?;
+        if let Some(RequestTimeout(timeout)) = timeout {
+            *req_reqwest.timeout_mut() = Some(timeout);
+        }
+
+        let started_at = Instant::now();
+        let mut res_reqwest = self.http_client.execute(req_reqwest).await?;
+        let time_to_first_byte = started_at.elapsed();
 
         let res = Response::new(());
         let (mut head, _) = res.into_parts();
         head.status = res_reqwest.status();
         head.version = res_reqwest.version();
         head.headers = res_reqwest.headers().to_owned();
+        if let Some(remote_addr) = res_reqwest.remote_addr() {
+            head.extensions.insert(RemoteAddr(remote_addr));
+        }
+        if let Ok(uri) = res_reqwest.url().as_str().parse() {
+            head.extensions.insert(EffectiveUri(uri));
+        }
 
-        let body = res_reqwest.bytes().await?.to_vec();
+        let body = match max_body_size {
+            Some(limit) => {
+                let too_large = ReqwestClientRespondError::BodyTooLarge { limit };
+                if res_reqwest.content_length().unwrap_or(0) > limit as u64 {
+                    return Err(too_large);
+                }
+
+                let mut body = Vec::new();
+                while let Some(chunk) = res_reqwest.chunk().await? {
+                    if body.len() + chunk.len() > limit {
+                        return Err(too_large);
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                body
+            }
+            None => res_reqwest.bytes().await?.to_vec(),
+        };
+
+        head.extensions.insert(ResponseTimings {
+            time_to_first_byte: Some(time_to_first_byte),
+            total: Some(started_at.elapsed()),
+            ..Default::default()
+        });
 
         let res = Response::from_parts(head, body);
 
@@ -52,8 +209,54 @@ impl Client for ReqwestClient {
 }
 
 #[async_trait]
-impl RetryableClient for ReqwestClient {
-    async fn sleep(&self, dur: Duration) {
-        tokio::time::sleep(dur).await;
+impl Client for ReqwestClient {
+    type RespondError = ReqwestClientRespondError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        #[cfg(feature = "tracing")]
+        let ret = http_api_client::instrumentation::instrument_respond(request, |request| {
+            self.execute(request)
+        })
+        .await;
+        #[cfg(not(feature = "tracing"))]
+        let ret = self.execute(request).await;
+
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+}
+
+impl WithSleeper for ReqwestClient {
+    fn sleeper(&self) -> &dyn Sleeper {
+        self.sleeper.as_ref()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum ReqwestClientRespondError {
+    RequestFailed(ReqwestError),
+    BodyTooLarge { limit: usize },
+    UnsupportedRequestOption(&'static str),
+}
+impl core::fmt::Display for ReqwestClientRespondError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for ReqwestClientRespondError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(err) => Some(err),
+            Self::BodyTooLarge { .. } | Self::UnsupportedRequestOption(_) => None,
+        }
+    }
+}
+impl From<ReqwestError> for ReqwestClientRespondError {
+    fn from(err: ReqwestError) -> Self {
+        Self::RequestFailed(err)
     }
 }
@@ -0,0 +1,275 @@
+use std::{
+    io::{BufRead as _, BufReader, Error as IoError, Write as _},
+    net::{SocketAddr, TcpListener},
+    thread,
+};
+
+use http_api_client_endpoint::{
+    Body, EffectiveUri, Endpoint, RedirectPolicy, RemoteAddr, Request, Response, ResponseTimings,
+};
+use http_api_reqwest_client::{
+    http_api_client::{
+        config::Proxy, ClientConfig, ClientRespondEndpointError, ProxyConfig, RedirectClient,
+    },
+    Client as _, ReqwestClient, ReqwestClientRespondError,
+};
+
+// Answers every request with its path as the body, redirecting `/redirect` to `/echo`.
+fn serve() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+
+                let body = request_line.split(' ').nth(1).unwrap_or_default();
+                let status = match body {
+                    "/redirect" => "302 Found\r\nLocation: /echo",
+                    _ => "200 OK",
+                };
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            });
+        }
+    });
+
+    addr
+}
+
+#[derive(Clone)]
+struct EchoEndpoint {
+    addr: SocketAddr,
+    path: &'static str,
+    max_response_body_size: Option<usize>,
+    redirect_policy: Option<RedirectPolicy>,
+}
+impl Endpoint for EchoEndpoint {
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = String;
+    type ParseResponseError = IoError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        Ok(Request::builder()
+            .uri(format!("http://{}{}", self.addr, self.path))
+            .body(vec![])
+            .unwrap())
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        Ok(String::from_utf8(response.into_body()).unwrap())
+    }
+
+    fn max_response_body_size(&self) -> Option<usize> {
+        self.max_response_body_size
+    }
+
+    fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        self.redirect_policy
+    }
+}
+
+#[tokio::test]
+async fn test_respond_endpoint() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+
+    let client = ReqwestClient::new().unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "/echo");
+}
+
+#[tokio::test]
+async fn test_max_response_body_size() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        max_response_body_size: Some(4),
+        redirect_policy: None,
+    };
+
+    let client = ReqwestClient::new().unwrap();
+    match client.respond_endpoint(&endpoint).await {
+        Err(ClientRespondEndpointError::RespondFailed(
+            ReqwestClientRespondError::BodyTooLarge { limit: 4 },
+        )) => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    let client = ReqwestClient::new().unwrap().with_max_response_body_size(4);
+    let endpoint = EchoEndpoint {
+        max_response_body_size: Some(1024),
+        ..endpoint
+    };
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "/echo");
+}
+
+#[tokio::test]
+async fn test_response_metadata() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+
+    let client = ReqwestClient::new().unwrap();
+    let mut extensions = None;
+    client
+        .respond_endpoint_with_callback(
+            &endpoint,
+            |req| req,
+            |res| extensions = Some(res.extensions().to_owned()),
+        )
+        .await
+        .unwrap();
+    let extensions = extensions.unwrap();
+    assert_eq!(extensions.get::<RemoteAddr>(), Some(&RemoteAddr(addr)));
+    assert_eq!(
+        extensions.get::<EffectiveUri>().unwrap().0,
+        format!("http://{}/echo", addr).as_str()
+    );
+    assert!(extensions.get::<ResponseTimings>().unwrap().total.is_some());
+}
+
+#[tokio::test]
+async fn test_unsupported_request_option() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+
+    let client = ReqwestClient::new().unwrap();
+    match client
+        .respond_endpoint_with_callback(
+            &endpoint,
+            |mut req| {
+                req.extensions_mut().insert(RedirectPolicy::None);
+                req
+            },
+            |_| {},
+        )
+        .await
+    {
+        Err(ClientRespondEndpointError::RespondFailed(
+            ReqwestClientRespondError::UnsupportedRequestOption("RedirectPolicy"),
+        )) => {}
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_client() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/redirect",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+
+    let client = ReqwestClient::new().unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "/echo");
+
+    let client = ReqwestClient::without_redirects().unwrap();
+    assert_eq!(
+        client.respond_endpoint(&endpoint).await.unwrap(),
+        "/redirect"
+    );
+
+    let client = RedirectClient::new(client);
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "/echo");
+
+    let endpoint = EchoEndpoint {
+        redirect_policy: Some(RedirectPolicy::None),
+        ..endpoint
+    };
+    assert_eq!(
+        client.respond_endpoint(&endpoint).await.unwrap(),
+        "/redirect"
+    );
+}
+
+#[tokio::test]
+async fn test_proxy() {
+    let addr = serve();
+    let unreachable_addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let client = ReqwestClient::from_config(
+        &ClientConfig::new().with_proxy(ProxyConfig::all(Proxy::new(format!("http://{}", addr)))),
+    )
+    .unwrap();
+    let endpoint = EchoEndpoint {
+        addr: unreachable_addr,
+        path: "/echo",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+    assert_eq!(
+        client.respond_endpoint(&endpoint).await.unwrap(),
+        format!("http://{}/echo", unreachable_addr)
+    );
+
+    let client = ReqwestClient::from_config(
+        &ClientConfig::new().with_proxy(
+            ProxyConfig::all(Proxy::new(format!("http://{}", unreachable_addr)))
+                .with_no_proxy("127.0.0.1"),
+        ),
+    )
+    .unwrap();
+    let endpoint = EchoEndpoint { addr, ..endpoint };
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "/echo");
+}
+
+#[tokio::test]
+async fn test_from_config() {
+    let addr = serve();
+    let endpoint = EchoEndpoint {
+        addr,
+        path: "/echo",
+        max_response_body_size: None,
+        redirect_policy: None,
+    };
+
+    let client =
+        ReqwestClient::from_config(&ClientConfig::new().with_max_response_body_size(4)).unwrap();
+    match client.respond_endpoint(&endpoint).await {
+        Err(ClientRespondEndpointError::RespondFailed(
+            ReqwestClientRespondError::BodyTooLarge { limit: 4 },
+        )) => {}
+        ret => panic!("{:?}", ret),
+    }
+}
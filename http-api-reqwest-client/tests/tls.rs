@@ -0,0 +1,245 @@
+use std::{
+    fs,
+    io::{BufRead as _, BufReader, Error as IoError, Write as _},
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use http_api_client_endpoint::{Body, Endpoint, Request, Response};
+use http_api_reqwest_client::{
+    http_api_client::{
+        config::{IdentityConfig, TlsVersion},
+        tls::public_key_pin,
+        ClientConfig,
+    },
+    Client as _, ReqwestClient,
+};
+use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKeyChain};
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedIssuer, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, PublicKeyData as _,
+};
+use rustls::{
+    crypto::ring::default_provider, pki_types::PrivatePkcs8KeyDer, server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig, ServerConnection, StreamOwned, SupportedProtocolVersion,
+};
+
+static SERVE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// Writes `ca.pem`, `client.pem`, `client.key` and `client.p12` (password `secret`) to a new directory, then answers `hello` on `localhost`
+// to clients with a certificate issued by that CA. Also returns the pin of the server's public key.
+fn serve(versions: &[&'static SupportedProtocolVersion]) -> (SocketAddr, PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!(
+        "http-api-reqwest-client-tls-{}-{}",
+        std::process::id(),
+        SERVE_COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut ca_params = CertificateParams::new(vec![]).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "Test CA");
+    let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let mut server_params = CertificateParams::new(vec!["localhost".to_owned()]).unwrap();
+    server_params
+        .distinguished_name
+        .push(DnType::CommonName, "localhost");
+    server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let server_cert = server_params.signed_by(&server_key, &ca).unwrap();
+
+    let client_key = KeyPair::generate().unwrap();
+    let mut client_params = CertificateParams::new(vec![]).unwrap();
+    client_params
+        .distinguished_name
+        .push(DnType::CommonName, "client");
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client_cert = client_params.signed_by(&client_key, &ca).unwrap();
+
+    fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+    fs::write(dir.join("client.pem"), client_cert.pem()).unwrap();
+    fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+    let mut key_store = KeyStore::new();
+    key_store.add_entry(
+        "client",
+        KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+            client_key.serialize_der(),
+            b"client",
+            [Certificate::from_der(client_cert.der()).unwrap()],
+        )),
+    );
+    fs::write(
+        dir.join("client.p12"),
+        key_store.writer("secret").write().unwrap(),
+    )
+    .unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(ca.der().to_owned()).unwrap();
+    let provider = Arc::new(default_provider());
+    let server_config = Arc::new(
+        ServerConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(versions)
+            .unwrap()
+            .with_client_cert_verifier(
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                    .build()
+                    .unwrap(),
+            )
+            .with_single_cert(
+                vec![server_cert.der().to_owned()],
+                PrivatePkcs8KeyDer::from(server_key.serialize_der()).into(),
+            )
+            .unwrap(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let server_config = server_config.clone();
+            thread::spawn(move || {
+                let conn = ServerConnection::new(server_config).unwrap();
+                let mut reader = BufReader::new(StreamOwned::new(conn, stream));
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                }
+
+                let stream = reader.get_mut();
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                );
+                stream.conn.send_close_notify();
+                let _ = stream.flush();
+            });
+        }
+    });
+
+    (
+        addr,
+        dir,
+        public_key_pin(&server_key.subject_public_key_info()),
+    )
+}
+
+#[derive(Clone)]
+struct HelloEndpoint {
+    addr: SocketAddr,
+}
+impl Endpoint for HelloEndpoint {
+    type RenderRequestError = IoError;
+
+    type ParseResponseOutput = String;
+    type ParseResponseError = IoError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        Ok(Request::builder()
+            .uri(format!("https://localhost:{}/hello", self.addr.port()))
+            .body(vec![])
+            .unwrap())
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        Ok(String::from_utf8(response.into_body()).unwrap())
+    }
+}
+
+fn client_config(dir: &Path) -> ClientConfig {
+    ClientConfig::new()
+        .with_root_certificate(dir.join("ca.pem"))
+        .without_built_in_root_certificates()
+        .with_identity(IdentityConfig::Pem {
+            cert: dir.join("client.pem"),
+            key: dir.join("client.key"),
+        })
+}
+
+#[tokio::test]
+async fn test_mutual_tls() {
+    let (addr, dir, _) = serve(rustls::DEFAULT_VERSIONS);
+    let endpoint = HelloEndpoint { addr };
+
+    let client = ReqwestClient::from_config(&client_config(&dir)).unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "hello");
+
+    let client = ReqwestClient::from_config(&ClientConfig {
+        identity: Some(IdentityConfig::Pkcs12 {
+            path: dir.join("client.p12"),
+            password: "secret".into(),
+        }),
+        ..client_config(&dir)
+    })
+    .unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "hello");
+
+    let client = ReqwestClient::from_config(&ClientConfig {
+        identity: None,
+        ..client_config(&dir)
+    })
+    .unwrap();
+    assert!(client.respond_endpoint(&endpoint).await.is_err());
+
+    let client = ReqwestClient::from_config(&ClientConfig {
+        root_certificates: vec![],
+        built_in_root_certificates: true,
+        ..client_config(&dir)
+    })
+    .unwrap();
+    assert!(client.respond_endpoint(&endpoint).await.is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_pinned_public_keys() {
+    let (addr, dir, server_pin) = serve(rustls::DEFAULT_VERSIONS);
+    let endpoint = HelloEndpoint { addr };
+
+    let client =
+        ReqwestClient::from_config(&client_config(&dir).with_pinned_public_key(server_pin))
+            .unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "hello");
+
+    let client = ReqwestClient::from_config(
+        &client_config(&dir).with_pinned_public_key(public_key_pin(b"")),
+    )
+    .unwrap();
+    assert!(client.respond_endpoint(&endpoint).await.is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_min_tls_version() {
+    let (addr, dir, _) = serve(&[&rustls::version::TLS12]);
+    let endpoint = HelloEndpoint { addr };
+
+    let client =
+        ReqwestClient::from_config(&client_config(&dir).with_min_tls_version(TlsVersion::Tls1_2))
+            .unwrap();
+    assert_eq!(client.respond_endpoint(&endpoint).await.unwrap(), "hello");
+
+    let client =
+        ReqwestClient::from_config(&client_config(&dir).with_min_tls_version(TlsVersion::Tls1_3))
+            .unwrap();
+    assert!(client.respond_endpoint(&endpoint).await.is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
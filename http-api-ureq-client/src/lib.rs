@@ -0,0 +1,242 @@
+pub use http_api_client;
+pub use ureq;
+
+use core::time::Duration;
+use std::{sync::Arc, time::Instant};
+
+pub use http_api_client::BlockingClient;
+use http_api_client::{
+    config::FromConfigError, http::HeaderMap, Body, ClientConfig, ClientMetrics, EffectiveUri,
+    MaxResponseBodySize, RedirectPolicy, Request, RequestProxy, RequestTimeout, Response,
+    ResponseTimings, TlsIdentity, VersionPreference,
+};
+use ureq::{Agent as UreqAgent, Error as UreqError, RequestExt as _, ResponseExt as _};
+
+#[derive(Debug, Clone)]
+pub struct UreqClient {
+    pub agent: UreqAgent,
+    pub metrics: Option<Arc<dyn ClientMetrics>>,
+    pub max_response_body_size: Option<usize>,
+    /// Added to each request that doesn't have them already.
+    pub default_headers: HeaderMap,
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UreqClient {
+    pub fn new() -> Self {
+        Self::with(
+            UreqAgent::config_builder()
+                .timeout_connect(Some(Duration::from_secs(5)))
+                .timeout_global(Some(Duration::from_secs(30)))
+                .http_status_as_error(false)
+                .build()
+                .new_agent(),
+        )
+    }
+
+    /// `agent` should be built with `http_status_as_error(false)`,
+    /// otherwise non-2xx responses never reach the endpoints.
+    pub fn with(agent: UreqAgent) -> Self {
+        Self {
+            agent,
+            metrics: None,
+            max_response_body_size: None,
+            default_headers: HeaderMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &ClientConfig) -> Result<Self, FromConfigError<UreqError>> {
+        if !config.root_certificates.is_empty() {
+            return Err(FromConfigError::Unsupported("root_certificates"));
+        }
+        if !config.built_in_root_certificates {
+            return Err(FromConfigError::Unsupported("built_in_root_certificates"));
+        }
+        if config.identity.is_some() {
+            return Err(FromConfigError::Unsupported("identity"));
+        }
+        if config.min_tls_version.is_some() {
+            return Err(FromConfigError::Unsupported("min_tls_version"));
+        }
+        if !config.pinned_public_keys.is_empty() {
+            return Err(FromConfigError::Unsupported("pinned_public_keys"));
+        }
+        if config.http_version == Some(VersionPreference::Http2PriorKnowledge) {
+            return Err(FromConfigError::Unsupported("http_version"));
+        }
+        if config
+            .proxy
+            .as_ref()
+            .is_some_and(|x| x.http.is_some() || x.https.is_some())
+        {
+            return Err(FromConfigError::Unsupported("proxy"));
+        }
+
+        let mut builder = UreqAgent::config_builder()
+            .timeout_connect(config.connect_timeout)
+            .timeout_global(config.timeout)
+            .http_status_as_error(false);
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder = builder.max_idle_connections_per_host(max);
+        }
+        if let Some(max_idle_age) = config.pool_idle_timeout {
+            builder = builder.max_idle_age(max_idle_age);
+        }
+        if config.proxy.is_some() {
+            builder = builder.proxy(None);
+        }
+
+        let mut client = Self::with(builder.build().new_agent());
+        client.max_response_body_size = config.max_response_body_size;
+        client.default_headers = config.header_map()?;
+        Ok(client)
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn ClientMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_max_response_body_size(mut self, max_response_body_size: usize) -> Self {
+        self.max_response_body_size = Some(max_response_body_size);
+        self
+    }
+
+    fn execute(
+        &self,
+        mut request: Request<Body>,
+    ) -> Result<Response<Body>, UreqClientRespondError> {
+        // ureq only takes these when building the agent, so they can't be honored per request.
+        let extensions = request.extensions();
+        for (present, name) in [
+            (extensions.get::<RequestProxy>().is_some(), "RequestProxy"),
+            (
+                matches!(
+                    extensions.get::<RedirectPolicy>(),
+                    Some(RedirectPolicy::SameOrigin(_))
+                ),
+                "RedirectPolicy",
+            ),
+            (
+                extensions.get::<VersionPreference>().is_some(),
+                "VersionPreference",
+            ),
+            (extensions.get::<TlsIdentity>().is_some(), "TlsIdentity"),
+        ] {
+            if present {
+                return Err(UreqClientRespondError::UnsupportedRequestOption(name));
+            }
+        }
+
+        for (name, value) in &self.default_headers {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name, value.to_owned());
+            }
+        }
+
+        let max_body_size = request
+            .extensions()
+            .get::<MaxResponseBodySize>()
+            .map(|MaxResponseBodySize(x)| *x)
+            .or(self.max_response_body_size);
+
+        let timeout = request.extensions().get::<RequestTimeout>().copied();
+        let max_redirects = match request.extensions().get::<RedirectPolicy>() {
+            Some(RedirectPolicy::None) => Some(0),
+            Some(RedirectPolicy::Limit(limit)) => Some(u32::try_from(*limit).unwrap_or(u32::MAX)),
+            Some(RedirectPolicy::SameOrigin(_)) | None => None,
+        };
+
+        let started_at = Instant::now();
+        let res = if timeout.is_some() || max_redirects.is_some() {
+            let mut config = request.with_agent(&self.agent).configure();
+            if let Some(RequestTimeout(timeout)) = timeout {
+                config = config.timeout_global(Some(timeout));
+            }
+            if let Some(max_redirects) = max_redirects {
+                config = config.max_redirects(max_redirects);
+            }
+            config.run()?
+        } else {
+            self.agent.run(request)?
+        };
+
+        let time_to_first_byte = started_at.elapsed();
+        let effective_uri = EffectiveUri(res.get_uri().to_owned());
+
+        let (mut head, mut body) = res.into_parts();
+        let body = body
+            .with_config()
+            .limit(max_body_size.map(|x| x as u64).unwrap_or(u64::MAX))
+            .read_to_vec()
+            .map_err(|err| match (err, max_body_size) {
+                (UreqError::BodyExceedsLimit(_), Some(limit)) => {
+                    UreqClientRespondError::BodyTooLarge { limit }
+                }
+                (err, _) => UreqClientRespondError::RequestFailed(err),
+            })?;
+
+        head.extensions.insert(effective_uri);
+        head.extensions.insert(ResponseTimings {
+            time_to_first_byte: Some(time_to_first_byte),
+            total: Some(started_at.elapsed()),
+            ..Default::default()
+        });
+
+        Ok(Response::from_parts(head, body))
+    }
+}
+
+impl BlockingClient for UreqClient {
+    type RespondError = UreqClientRespondError;
+
+    fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        #[cfg(feature = "tracing")]
+        let ret =
+            http_api_client::instrumentation::instrument_respond_blocking(request, |request| {
+                self.execute(request)
+            });
+        #[cfg(not(feature = "tracing"))]
+        let ret = self.execute(request);
+
+        ret
+    }
+
+    fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum UreqClientRespondError {
+    RequestFailed(UreqError),
+    BodyTooLarge { limit: usize },
+    UnsupportedRequestOption(&'static str),
+}
+impl core::fmt::Display for UreqClientRespondError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for UreqClientRespondError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RequestFailed(err) => Some(err),
+            Self::BodyTooLarge { .. } | Self::UnsupportedRequestOption(_) => None,
+        }
+    }
+}
+impl From<UreqError> for UreqClientRespondError {
+    fn from(err: UreqError) -> Self {
+        Self::RequestFailed(err)
+    }
+}